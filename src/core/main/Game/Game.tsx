@@ -12,6 +12,16 @@ const FPS_THROTTLE = 1000 / 144; // 144 fps
 const UPDATE_THROTTLE = 1000 / 288; // 144 fps
 const USER_INPUT_THROTTLE = 1000 / 500; // 144 fps
 
+/* Events drained from `GlClient.poll_events`, only the fields used here */
+interface GameEvent {
+  type: string;
+  score?: number;
+  gained?: number;
+  lives?: number;
+  wave?: number;
+  to?: string;
+}
+
 /* Component */
 interface Props {
   wasm: typeof import('wasm-app');
@@ -30,8 +40,11 @@ export function Game({ wasm }: Props): ReactElement {
   /* Game state */
   const [gameState, setGameState] = useState(GameState.INITIALIZING);
   const [score, setScore] = useState(0);
+  const [gained, setGained] = useState(0);
+  const [wave, setWave] = useState(0);
   const [lives, setLives] = useState(3);
   const [seconds, setSeconds] = useState(0);
+  const pausedRef = React.useRef(false);
   const [interval, setIntervalVariable] = useState<NodeJS.Timeout | null>(null);
 
   /* Game initialisation */
@@ -39,6 +52,29 @@ export function Game({ wasm }: Props): ReactElement {
     setCanvas(new wasm.CanvasData(width, height, degrees, canvasId));
   }, [width, height, canvasId, wasm.CanvasData, degrees]);
 
+  /* Game events, polled every frame */
+  const handleEvent = useCallback((event: GameEvent) => {
+    if (event.type === 'score') {
+      setScore(event.score ?? 0);
+      setGained(event.gained ?? 0);
+    } else if (event.type === 'ship_destroyed' || event.type === 'life_gained') {
+      setLives(event.lives ?? 0);
+    } else if (event.type === 'state') {
+      pausedRef.current = event.to === 'paused';
+      if (event.to === 'game_over') {
+        setGameState(GameState.GAME_OVER);
+      }
+    } else if (event.type === 'wave') {
+      setWave(event.wave ?? 0);
+    } else if (event.type === 'hyperspace') {
+      // Flash the screen while the ship is in hyperspace
+      document.getElementById(canvasId)?.animate(
+        [{ filter: 'brightness(3)' }, { filter: 'none' }],
+        { duration: 250 },
+      );
+    }
+  }, [canvasId]);
+
   // Use useRef for mutable variables that we want to persist
   // without triggering a re-render on their change
   const requestRef = React.useRef<number>();
@@ -57,6 +93,7 @@ export function Game({ wasm }: Props): ReactElement {
         && time - previousTimeRef.current > UPDATE_THROTTLE
       ) {
         client?.update(time - (previousTimeRef.current || 0));
+        client?.poll_events().forEach(handleEvent);
       }
       if (
         previousTimeRef.current !== undefined
@@ -67,7 +104,7 @@ export function Game({ wasm }: Props): ReactElement {
       previousTimeRef.current = time;
       requestRef.current = requestAnimationFrame(animate);
     },
-    [client],
+    [client, handleEvent],
   );
 
   useEffect(() => {
@@ -123,6 +160,14 @@ export function Game({ wasm }: Props): ReactElement {
           client?.keyboard_s(true);
         } else if (e.key === ' ') {
           client?.keyboard_space(true);
+        } else if (e.key === 'Shift') {
+          client?.keyboard_shift(true);
+        } else if (e.key === 'p') {
+          if (pausedRef.current) {
+            client?.resume();
+          } else {
+            client?.pause();
+          }
         }
       }
     },
@@ -141,22 +186,22 @@ export function Game({ wasm }: Props): ReactElement {
           client?.keyboard_s(false);
         } else if (e.key === ' ') {
           client?.keyboard_space(false);
+        } else if (e.key === 'Shift') {
+          client?.keyboard_shift(false);
         }
       }
     },
     [client, rectEl],
   );
 
-  /* Set the factual renderable object */
+  /* Set the factual renderable object, it shows the attract screen until started */
   useEffect(() => {
-    if (gameState === GameState.RUNNING) {
-      client?.set_renderable(wasm.RenderableOption.Asteroid, new wasm.Transform(0, 0, 0));
-      client?.set_score_function(
-        (scoreNew: number, livesNew: number) => {
-          setScore(scoreNew); setLives(livesNew);
-        });
+    if (client !== undefined) {
+      client.set_renderable(wasm.RenderableOption.Asteroid, new wasm.Transform(0, 0, 0));
+      // Only known once the renderable is set up, it may have fallen back to WebGL1
+      console.info(`Rendering with ${client.backend()}`);
     }
-  }, [client, gameState, wasm.RenderableOption.Asteroid, wasm.Transform]);
+  }, [client, wasm.RenderableOption.Asteroid, wasm.Transform]);
 
   /* Handle game state changes */
   useEffect(() => {
@@ -164,7 +209,7 @@ export function Game({ wasm }: Props): ReactElement {
     if (gameState === GameState.RUNNING) {
       setIntervalVariable(
         setInterval(() => {
-          if (gameState === GameState.RUNNING) {
+          if (gameState === GameState.RUNNING && !pausedRef.current) {
             setSeconds((s) => s + 1);
           }
         }, 1000),
@@ -173,13 +218,10 @@ export function Game({ wasm }: Props): ReactElement {
   }, [gameState]);
 
   useEffect(() => {
-    if (lives <= 0) {
-      setGameState(GameState.GAME_OVER);
-      if (interval !== null) {
-        clearInterval(interval);
-      }
+    if (gameState === GameState.GAME_OVER && interval !== null) {
+      clearInterval(interval);
     }
-  }, [interval, lives]);
+  }, [gameState, interval]);
 
 
   /* Autofocus the canvas */
@@ -200,12 +242,14 @@ export function Game({ wasm }: Props): ReactElement {
           && (
           <GameOverlay
             setActive={() => {
-              const tmpClient = new wasm.GlClient(canvas!);
-              setClient(tmpClient); setGameState(GameState.RUNNING);
+              // Reuses the GL context, the previous game's buffers are released
+              client?.restart();
+              setGameState(GameState.RUNNING);
               setLives(3);
               setScore(0);
+              setGained(0);
+              setWave(0);
               setSeconds(0);
-              setCanvas(new wasm.CanvasData(width, height, degrees, canvasId));
             }}
             currentState={gameState}
             score={score}
@@ -222,9 +266,10 @@ export function Game({ wasm }: Props): ReactElement {
           onMouseMove={mouseMove}
           onKeyDown={keyDown}
           onKeyUp={keyUp}
+          onBlur={() => client?.pause()}
         />
         {wasm && canvas && client && gameState !== GameState.INITIALIZING && (
-          <Status lives={lives} score={score} seconds={seconds} />
+          <Status lives={lives} score={score} gained={gained} wave={wave} seconds={seconds} />
         )}
       </div>
       <div className={style['error-message']}>
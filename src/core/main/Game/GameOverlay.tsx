@@ -38,6 +38,12 @@ export function GameOverlay({
           <div>
             SPACE - Shoot
           </div>
+          <div>
+            SHIFT - Hyperspace
+          </div>
+          <div>
+            P - Pause
+          </div>
           <button
             className={`${style.btn} ${style['draw-border']}`}
             type="button"
@@ -5,15 +5,20 @@ import styleG from './Game.module.scss';
 interface Props {
   lives: number;
   score: number;
+  gained: number;
+  wave: number;
   seconds: number;
 }
 
 export function Status({
   lives,
   score,
+  gained,
+  wave,
   seconds,
 }: Props): ReactElement {
-  const maxLives = 3;
+  // Extra lives add hearts past the starting three
+  const maxLives = Math.max(3, lives);
   const heartsEmpty = Array.apply(null, Array(maxLives - lives)).map((_, index) => <i className={`${'far fa-heart '}${styleG.heart}`} key={index} />);
   const heartsFull = Array.apply(null, Array(lives)).map((_, index) => <i className={`${'fas fa-heart '}${styleG.heart}`} key={index} />);
   return (
@@ -21,6 +26,10 @@ export function Status({
       <h3>Status</h3>
       <div>
         Score: {score}
+        {gained > 0 && ` (+${gained})`}
+      </div>
+      <div>
+        Wave: {wave}
       </div>
       <div>
         seconds: {seconds}
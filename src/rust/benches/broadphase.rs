@@ -0,0 +1,81 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::prelude::*;
+use rand_pcg::Pcg32;
+
+use spa_wasm_rust::programs::asteroid::broadphase::SpatialHash;
+use spa_wasm_rust::programs::asteroid::entity::{EntityStore, Handle};
+use spa_wasm_rust::programs::asteroid::GameObject;
+
+fn object<R: Rng>(rng: &mut R, radius: f32) -> GameObject {
+    let mut obj = GameObject::new();
+    obj.position = bevy_math::Vec2::new(rng.gen_range(-11., 11.), rng.gen_range(-8., 8.));
+    obj.radius = radius;
+    obj
+}
+
+/// Asteroids of the sizes the game produces and as many bullets
+fn scene(asteroid_count: usize) -> (EntityStore<GameObject>, Vec<GameObject>) {
+    let mut rng = Pcg32::seed_from_u64(asteroid_count as u64);
+    let mut asteroids = EntityStore::new();
+    for _ in 0..asteroid_count {
+        let radius = rng.gen_range(0.1, 1.);
+        asteroids.spawn(object(&mut rng, radius));
+    }
+    let bullets = (0..asteroid_count).map(|_| object(&mut rng, 0.)).collect();
+    (asteroids, bullets)
+}
+
+fn brute_force(asteroids: &EntityStore<GameObject>, bullets: &[GameObject]) -> usize {
+    bullets
+        .iter()
+        .filter(|bullet| {
+            asteroids
+                .values()
+                .any(|asteroid| GameObject::does_overlap(bullet, asteroid))
+        })
+        .count()
+}
+
+fn spatial_hash(
+    grid: &mut SpatialHash,
+    asteroids: &EntityStore<GameObject>,
+    bullets: &[GameObject],
+) -> usize {
+    grid.clear();
+    for (handle, asteroid) in asteroids.iter() {
+        grid.insert(handle, asteroid.position, asteroid.radius);
+    }
+    let mut candidates: Vec<Handle> = vec![];
+    bullets
+        .iter()
+        .filter(|bullet| {
+            grid.query(bullet.position, bullet.radius, &mut candidates);
+            candidates
+                .iter()
+                .any(|handle| GameObject::does_overlap(bullet, asteroids.get(*handle).unwrap()))
+        })
+        .count()
+}
+
+fn bullets_against_asteroids(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bullets against asteroids");
+    for &count in &[70, 500, 2000, 5000] {
+        let (asteroids, bullets) = scene(count);
+        let mut grid = SpatialHash::default();
+        assert_eq!(
+            brute_force(&asteroids, &bullets),
+            spatial_hash(&mut grid, &asteroids, &bullets)
+        );
+
+        group.bench_with_input(BenchmarkId::new("brute force", count), &count, |b, _| {
+            b.iter(|| brute_force(&asteroids, &bullets))
+        });
+        group.bench_with_input(BenchmarkId::new("spatial hash", count), &count, |b, _| {
+            b.iter(|| spatial_hash(&mut grid, &asteroids, &bullets))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bullets_against_asteroids);
+criterion_main!(benches);
@@ -23,11 +23,11 @@ impl CanvasData {
     }
 
     pub fn calculate_aspect(width: f32, height: f32) -> f32 {
-        width / height as f32
+        width / height
     }
 
     pub fn calculate_fov(degrees: f32) -> f32 {
-        (degrees * PI / 180.) as f32
+        degrees * PI / 180.
     }
 
     #[wasm_bindgen]
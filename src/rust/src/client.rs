@@ -1,72 +1,203 @@
+use crate::gl_setup::{GlContext, GlVersion};
+use crate::gpu::{self, GpuProgram, ResourceCounts};
 use crate::programs::asteroid;
+use crate::programs::asteroid::events::EventQueue;
+use crate::programs::asteroid::replay;
 use crate::programs::box_2d::Box2D;
 use crate::programs::cube::Cube;
-use crate::shaders::fragment::F_SHADER;
-use crate::shaders::vertex::V_SHADER;
+use crate::shaders::fragment::{F_SHADER, F_SHADER_300};
+use crate::shaders::vertex::{V_SHADER, V_SHADER_300};
+use crate::timestep::FixedTimestep;
 use crate::transform::Transform;
 use crate::RenderableOption;
 use crate::{canvas::CanvasData, programs::asteroid::AsteroidCanvas};
-use crate::{
-    gl_setup,
-    utils::{console_log, link_program},
-    RenderObjectTrait,
-};
-use js_sys::Function;
+use crate::{gl_setup, utils::console_log, RenderObjectTrait};
+use js_sys::Array;
 use wasm_bindgen::prelude::*;
-use web_sys::{HtmlCanvasElement, WebGlProgram, WebGlRenderingContext as GL};
-
+use web_sys::{HtmlCanvasElement, WebGlRenderingContext as GL};
 
 #[wasm_bindgen]
 pub struct GlClient {
-    gl: GL,
+    gl: GlContext,
+    /// Rendering path in use: WebGL2 unless the context or a renderable could
+    /// only set up WebGL1
+    version: GlVersion,
     object: Option<Box<dyn RenderObjectTrait>>,
     pub is_ready: bool,
-    master_canvas: HtmlCanvasElement,
     canvas: CanvasData,
-    set_score: Option<Function>
+    events: EventQueue,
+    seed: Option<u64>,
+    timestep: FixedTimestep,
 }
 
 #[wasm_bindgen]
 impl GlClient {
     pub fn new(opt: RenderableOption, canvas: &CanvasData, transform: &Transform) -> Self {
         let canvas_el: HtmlCanvasElement = gl_setup::get_canvas(&canvas.get_canvas());
-        let gl: GL = gl_setup::initialize_webgl_context(&canvas_el).unwrap();
+        let gl = gl_setup::initialize_webgl_context(&canvas_el).unwrap();
+        let version = gl.version();
         let mut client: GlClient = GlClient {
             canvas: canvas.clone(),
             gl,
+            version,
             object: None,
             is_ready: false,
-            master_canvas: canvas_el,
-            set_score: None,
+            events: EventQueue::new(),
+            seed: None,
+            timestep: FixedTimestep::default(),
         };
-        client.set_renderable(opt, transform);
+        client.set_renderable(opt, transform).unwrap();
         client
     }
 
     #[wasm_bindgen(constructor)]
     pub fn new_default(canvas: &CanvasData) -> Self {
         let canvas_el: HtmlCanvasElement = gl_setup::get_canvas(&canvas.get_canvas());
-        let gl: GL = gl_setup::initialize_webgl_context(&canvas_el).unwrap();
+        let gl = gl_setup::initialize_webgl_context(&canvas_el).unwrap();
+        let version = gl.version();
         GlClient {
             canvas: canvas.clone(),
             gl,
+            version,
             object: None,
             is_ready: false,
-            master_canvas: canvas_el,
-            set_score: None,
+            events: EventQueue::new(),
+            seed: None,
+            timestep: FixedTimestep::default(),
+        }
+    }
+
+    /// Game events raised since the last call, as objects with their name under `type`.
+    /// Meant to be called once per frame, the events queue up until then.
+    #[wasm_bindgen]
+    pub fn poll_events(&mut self) -> Array {
+        self.events
+            .drain()
+            .iter()
+            .map(|event| event.to_js())
+            .collect()
+    }
+
+    /// Seed the random source; restarts a game in progress, is reused by
+    /// `restart` and applies to every renderable set afterwards
+    #[wasm_bindgen]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        if let Some(obj) = &mut self.object {
+            obj.set_seed(seed);
+        }
+    }
+
+    /// Replay of the running session, empty if there is none
+    #[wasm_bindgen]
+    pub fn get_recording(&self) -> Vec<u8> {
+        self.object
+            .as_ref()
+            .and_then(|obj| obj.recording())
+            .unwrap_or_default()
+    }
+
+    /// Restart the session and play back a recording from `get_recording`;
+    /// live keyboard input is ignored until a new seed is set
+    #[wasm_bindgen]
+    pub fn play_recording(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        match &mut self.object {
+            Some(obj) => {
+                obj.play_recording(data)
+                    .map_err(|e| JsValue::from_str(&e))?;
+                self.timestep.reset();
+                Ok(())
+            }
+            None => Err(JsValue::from_str("No renderable set")),
         }
     }
 
+    /// `get_recording` as URL-safe base64, for sharing runs as links
     #[wasm_bindgen]
-    pub fn set_score_function(&mut self, callback: Function) {
-        self.set_score = Some(callback);
+    pub fn get_recording_base64(&self) -> String {
+        replay::to_base64(&self.get_recording())
+    }
+
+    #[wasm_bindgen]
+    pub fn play_recording_base64(&mut self, data: &str) -> Result<(), JsValue> {
+        let bytes = replay::from_base64(data).map_err(|e| JsValue::from_str(&e))?;
+        self.play_recording(&bytes)
+    }
+
+    /// Snapshot of the running session, empty if there is none
+    #[wasm_bindgen]
+    pub fn save_state(&self) -> Vec<u8> {
+        self.object
+            .as_ref()
+            .and_then(|obj| obj.save_state())
+            .unwrap_or_default()
+    }
+
+    /// Resume a session from `save_state`, e.g. after the tab was reloaded
+    #[wasm_bindgen]
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        match &mut self.object {
+            Some(obj) => {
+                obj.load_state(data).map_err(|e| JsValue::from_str(&e))?;
+                self.timestep.reset();
+                Ok(())
+            }
+            None => Err(JsValue::from_str("No renderable set")),
+        }
+    }
+
+    /// Freeze the game; state changes are queued as `state` events and read through
+    /// `poll_events`
+    #[wasm_bindgen]
+    pub fn pause(&mut self) {
+        if let Some(obj) = &mut self.object {
+            obj.pause();
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn resume(&mut self) {
+        if let Some(obj) = &mut self.object {
+            obj.resume();
+            // Do not catch up on the time spent paused
+            self.timestep.reset();
+        }
+    }
+
+    /// Start a new game on the same GL context, reusing its buffers
+    #[wasm_bindgen]
+    pub fn restart(&mut self) {
+        if let Some(obj) = &mut self.object {
+            obj.restart();
+            self.timestep.reset();
+        }
+    }
+
+    /// Rendering path picked for this client, `webgl2` or the `webgl1` fallback
+    #[wasm_bindgen]
+    pub fn backend(&self) -> String {
+        self.version.name().to_string()
+    }
+
+    /// Buffers and programs alive on the GPU, for spotting leaks
+    #[wasm_bindgen]
+    pub fn gpu_resources(&self) -> ResourceCounts {
+        gpu::live_resources()
+    }
+
+    #[wasm_bindgen]
+    pub fn is_replaying(&self) -> bool {
+        self.object
+            .as_ref()
+            .map(|obj| obj.is_replaying())
+            .unwrap_or(false)
     }
 
     #[wasm_bindgen]
     pub fn render(&mut self) {
         match &mut self.object {
             Some(obj) => {
-                obj.draw_scene(&self.gl, &self.canvas);
+                obj.draw_scene(&self.canvas, self.timestep.alpha());
             }
             None => {
                 console_log("Clearing the canvas");
@@ -75,43 +206,69 @@ impl GlClient {
         }
     }
 
+    /// Fails if the renderable cannot be set up on the context, the canvas
+    /// stays clear until another one is set
     #[wasm_bindgen]
-    pub fn set_renderable(&mut self, opt: RenderableOption, transform: &Transform) {
+    pub fn set_renderable(
+        &mut self,
+        opt: RenderableOption,
+        transform: &Transform,
+    ) -> Result<(), JsValue> {
         console_log(&format!("Setting rendarble to {:?}", &opt));
 
         self.is_ready = false;
+        self.timestep.reset();
 
-        let gl: GL = gl_setup::initialize_webgl_context(&self.master_canvas).unwrap();
-        self.gl = gl;
+        // Release the buffers and program of the previous renderable first,
+        // the context of the canvas is reused as is
+        self.object = None;
+        self.clear();
+        let (vertex, fragment) = match self.version {
+            GlVersion::WebGl1 => (V_SHADER, F_SHADER),
+            GlVersion::WebGl2 => (V_SHADER_300, F_SHADER_300),
+        };
         match opt {
             RenderableOption::Cube => {
-                let program: WebGlProgram = link_program(&self.gl, &V_SHADER, &F_SHADER).unwrap();
-                self.gl.use_program(Some(&program));
-                let object: Box<Cube> =
-                    Box::new(RenderObjectTrait::new(&self.gl, program, transform.clone()));
+                let program = GpuProgram::link(&self.gl, vertex, fragment)
+                    .map_err(|e| JsValue::from_str(&e))?;
+                self.gl.use_program(Some(program.raw()));
+                let object: Box<Cube> = Box::new(
+                    RenderObjectTrait::new(&self.gl, self.version, program, *transform)
+                        .map_err(|e| JsValue::from_str(&e))?,
+                );
                 self.object = Some(object);
             }
             RenderableOption::Box2D => {
-                let program: WebGlProgram = link_program(&self.gl, &V_SHADER, &F_SHADER).unwrap();
-                self.gl.use_program(Some(&program));
-                let object: Box<Box2D> =
-                    Box::new(RenderObjectTrait::new(&self.gl, program, transform.clone()));
+                let program = GpuProgram::link(&self.gl, vertex, fragment)
+                    .map_err(|e| JsValue::from_str(&e))?;
+                self.gl.use_program(Some(program.raw()));
+                let object: Box<Box2D> = Box::new(
+                    RenderObjectTrait::new(&self.gl, self.version, program, *transform)
+                        .map_err(|e| JsValue::from_str(&e))?,
+                );
                 self.object = Some(object);
             }
             RenderableOption::Asteroid => {
-                let program: WebGlProgram = link_program(
-                    &self.gl,
-                    &asteroid::shaders::V_SHADER,
-                    &asteroid::shaders::F_SHADER,
-                )
-                .unwrap();
-                self.gl.use_program(Some(&program));
-                let object: Box<AsteroidCanvas> =
-                    Box::new(RenderObjectTrait::new(&self.gl, program, transform.clone()));
+                let (vertex, fragment) = asteroid::shaders::sources(self.version);
+                let program = GpuProgram::link(&self.gl, vertex, fragment)
+                    .map_err(|e| JsValue::from_str(&e))?;
+                self.gl.use_program(Some(program.raw()));
+                let object: Box<AsteroidCanvas> = Box::new(
+                    RenderObjectTrait::new(&self.gl, self.version, program, *transform)
+                        .map_err(|e| JsValue::from_str(&e))?,
+                );
                 self.object = Some(object);
             }
         }
+        // Stay on the fallback, later renderables would only fail the same way
+        if let Some(version) = self.object.as_ref().and_then(|obj| obj.version()) {
+            self.version = version;
+        }
+        if let (Some(obj), Some(seed)) = (&mut self.object, self.seed) {
+            obj.set_seed(seed);
+        }
         self.is_ready = true;
+        Ok(())
     }
 
     fn clear(&self) {
@@ -122,17 +279,14 @@ impl GlClient {
 
     #[wasm_bindgen]
     pub fn get_transform(&mut self) -> Option<Transform> {
-        match &mut self.object {
-            Some(obj) => Some(obj.transform().clone()),
-            None => None,
-        }
+        self.object.as_mut().map(|obj| *obj.transform())
     }
 
     #[wasm_bindgen]
     pub fn set_transform(&mut self, new_transform: &Transform) {
         match &mut self.object {
             Some(obj) => {
-                obj.set_transform(new_transform.clone());
+                obj.set_transform(*new_transform);
             }
             None => {
                 console_log("doing Nothing");
@@ -175,11 +329,24 @@ impl GlClient {
         }
     }
 
+    /// Hyperspace jump
+    #[wasm_bindgen]
+    pub fn keyboard_shift(&mut self, value: bool) {
+        match &mut self.object {
+            Some(obj) => {
+                obj.input().update_keyboard_shift(value);
+            }
+            None => {
+                console_log("doing Nothing");
+            }
+        }
+    }
+
     #[wasm_bindgen]
     pub fn keyboard_w(&mut self, value: bool) {
         match &mut self.object {
             Some(obj) => {
-                obj.input().update_keyboard_w( value);
+                obj.input().update_keyboard_w(value);
             }
             None => {
                 console_log("doing Nothing");
@@ -190,7 +357,7 @@ impl GlClient {
     pub fn keyboard_a(&mut self, value: bool) {
         match &mut self.object {
             Some(obj) => {
-                obj.input().update_keyboard_a( value);
+                obj.input().update_keyboard_a(value);
             }
             None => {
                 console_log("doing Nothing");
@@ -201,7 +368,7 @@ impl GlClient {
     pub fn keyboard_s(&mut self, value: bool) {
         match &mut self.object {
             Some(obj) => {
-                obj.input().update_keyboard_s( value);
+                obj.input().update_keyboard_s(value);
             }
             None => {
                 console_log("doing Nothing");
@@ -212,7 +379,7 @@ impl GlClient {
     pub fn keyboard_d(&mut self, value: bool) {
         match &mut self.object {
             Some(obj) => {
-                obj.input().update_keyboard_d( value);
+                obj.input().update_keyboard_d(value);
             }
             None => {
                 console_log("doing Nothing");
@@ -223,12 +390,11 @@ impl GlClient {
     pub fn update(&mut self, delta_time: f32) {
         match &mut self.object {
             Some(obj) => {
-                match &self.set_score {
-                    Some(f) => {
-                        obj.update(delta_time, &self.gl, &self.canvas, f);
-                    }
-                    None => {}
+                // Simulate in fixed steps so the game plays the same at any frame rate
+                for _ in 0..self.timestep.advance(delta_time) {
+                    obj.update(self.timestep.step(), &self.canvas);
                 }
+                self.events.extend(obj.drain_events());
             }
             None => {
                 console_log("doing Nothing");
@@ -11,28 +11,153 @@ fn wait_until_canvas_is_rendered(
     match canvas {
         Some(elem) => elem.dyn_into::<web_sys::HtmlCanvasElement>(),
         None => {
-            return wait_until_canvas_is_rendered(document, canvas_id);
+            wait_until_canvas_is_rendered(document, canvas_id)
         }
     }
 }
 
-pub fn initialize_webgl_context(
-    canvas: &HtmlCanvasElement,
-) -> Result<WebGlRenderingContext, JsValue> {
+/// WebGL version of a context, programs pick their shader variants by it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlVersion {
+    WebGl1,
+    WebGl2,
+}
+
+impl GlVersion {
+    pub fn name(self) -> &'static str {
+        match self {
+            GlVersion::WebGl1 => "webgl1",
+            GlVersion::WebGl2 => "webgl2",
+        }
+    }
+
+}
+
+/// Context of the canvas, either version. The two are unrelated types in
+/// web-sys, the calls the programs make exist on both and are passed on to
+/// whichever this holds. Constants are the same in both, they are read off
+/// `WebGlRenderingContext`.
+#[derive(Debug, Clone)]
+pub enum GlContext {
+    WebGl1(WebGlRenderingContext),
+    WebGl2(WebGl2RenderingContext),
+}
+
+macro_rules! forward {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)* $(,)?) $(-> $ret:ty)?;)*) => {
+        $(
+            pub fn $name(&self $(, $arg: $ty)*) $(-> $ret)? {
+                match self {
+                    GlContext::WebGl1(gl) => gl.$name($($arg),*),
+                    GlContext::WebGl2(gl) => gl.$name($($arg),*),
+                }
+            }
+        )*
+    };
+}
+
+impl GlContext {
+    pub fn version(&self) -> GlVersion {
+        match self {
+            GlContext::WebGl1(_) => GlVersion::WebGl1,
+            GlContext::WebGl2(_) => GlVersion::WebGl2,
+        }
+    }
+
+    /// The WebGL2 context, for the calls only it has
+    pub fn webgl2(&self) -> Option<&WebGl2RenderingContext> {
+        match self {
+            GlContext::WebGl1(_) => None,
+            GlContext::WebGl2(gl) => Some(gl),
+        }
+    }
+
+    forward! {
+        fn attach_shader(&self, program: &WebGlProgram, shader: &WebGlShader);
+        fn bind_buffer(&self, target: u32, buffer: Option<&WebGlBuffer>);
+        fn buffer_data_with_array_buffer_view(
+            &self,
+            target: u32,
+            data: &js_sys::Object,
+            usage: u32,
+        );
+        fn buffer_data_with_i32(&self, target: u32, size: i32, usage: u32);
+        fn buffer_sub_data_with_i32_and_array_buffer_view(
+            &self,
+            target: u32,
+            offset: i32,
+            data: &js_sys::Object,
+        );
+        fn clear(&self, mask: u32);
+        fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32);
+        fn clear_depth(&self, depth: f32);
+        fn compile_shader(&self, shader: &WebGlShader);
+        fn create_buffer(&self) -> Option<WebGlBuffer>;
+        fn create_program(&self) -> Option<WebGlProgram>;
+        fn create_shader(&self, shader_type: u32) -> Option<WebGlShader>;
+        fn delete_buffer(&self, buffer: Option<&WebGlBuffer>);
+        fn delete_program(&self, program: Option<&WebGlProgram>);
+        fn delete_shader(&self, shader: Option<&WebGlShader>);
+        fn depth_func(&self, func: u32);
+        fn disable_vertex_attrib_array(&self, index: u32);
+        fn draw_arrays(&self, mode: u32, first: i32, count: i32);
+        fn draw_elements_with_i32(&self, mode: u32, count: i32, index_type: u32, offset: i32);
+        fn enable(&self, cap: u32);
+        fn enable_vertex_attrib_array(&self, index: u32);
+        fn get_attrib_location(&self, program: &WebGlProgram, name: &str) -> i32;
+        fn get_extension(&self, name: &str) -> Result<Option<js_sys::Object>, JsValue>;
+        fn get_program_info_log(&self, program: &WebGlProgram) -> Option<String>;
+        fn get_program_parameter(&self, program: &WebGlProgram, pname: u32) -> JsValue;
+        fn get_shader_info_log(&self, shader: &WebGlShader) -> Option<String>;
+        fn get_shader_parameter(&self, shader: &WebGlShader, pname: u32) -> JsValue;
+        fn get_uniform_location(
+            &self,
+            program: &WebGlProgram,
+            name: &str,
+        ) -> Option<WebGlUniformLocation>;
+        fn link_program(&self, program: &WebGlProgram);
+        fn shader_source(&self, shader: &WebGlShader, source: &str);
+        fn uniform_matrix4fv_with_f32_array(
+            &self,
+            location: Option<&WebGlUniformLocation>,
+            transpose: bool,
+            data: &[f32],
+        );
+        fn use_program(&self, program: Option<&WebGlProgram>);
+        fn vertex_attrib_pointer_with_i32(
+            &self,
+            index: u32,
+            size: i32,
+            data_type: u32,
+            normalized: bool,
+            stride: i32,
+            offset: i32,
+        );
+    }
+}
+
+/// Prefers a WebGL2 context and falls back to WebGL1
+pub fn initialize_webgl_context(canvas: &HtmlCanvasElement) -> Result<GlContext, JsValue> {
     loop {
+        if let Some(gl2) = canvas.get_context("webgl2")? {
+            let gl = GlContext::WebGl2(gl2.dyn_into()?);
+            clear(&gl);
+            return Ok(gl);
+        }
         let gl = canvas.get_context("webgl")?;
-        match gl {
-            Some(gl_inner) => {
-                let gl: WebGlRenderingContext = gl_inner.dyn_into()?;
-                gl.clear_color(0.0, 0.0, 0.0, 1.0); //RGBA
-                gl.clear(GL::COLOR_BUFFER_BIT);
-                return Ok(gl);
-            }
-            _ => (),
+        if let Some(gl_inner) = gl {
+            let gl = GlContext::WebGl1(gl_inner.dyn_into()?);
+            clear(&gl);
+            return Ok(gl);
         }
     }
 }
 
+fn clear(gl: &GlContext) {
+    gl.clear_color(0.0, 0.0, 0.0, 1.0); //RGBA
+    gl.clear(GL::COLOR_BUFFER_BIT);
+}
+
 pub fn get_canvas(canvas_id: &str) -> HtmlCanvasElement {
     let window = window().unwrap();
     let document = window.document().unwrap();
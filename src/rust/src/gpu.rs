@@ -0,0 +1,138 @@
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlVertexArrayObject};
+
+use crate::gl_setup::GlContext;
+use crate::utils::link_program;
+
+/// GPU objects currently alive, a long session should keep these flat
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceCounts {
+    pub buffers: u32,
+    pub programs: u32,
+    /// Only created on WebGL2
+    pub vertex_arrays: u32,
+}
+
+thread_local! {
+    // WebGL lives on a single thread, so does the bookkeeping
+    static LIVE: Cell<ResourceCounts> = Cell::new(ResourceCounts::default());
+}
+
+pub fn live_resources() -> ResourceCounts {
+    LIVE.with(|live| live.get())
+}
+
+fn track(change: impl FnOnce(&mut ResourceCounts)) {
+    LIVE.with(|live| {
+        let mut counts = live.get();
+        change(&mut counts);
+        live.set(counts);
+    });
+}
+
+/// Buffer released together with the value owning it
+pub struct GpuBuffer {
+    gl: GlContext,
+    buffer: WebGlBuffer,
+}
+
+impl GpuBuffer {
+    pub fn new(gl: &GlContext) -> Result<Self, String> {
+        let buffer = gl
+            .create_buffer()
+            .ok_or_else(|| String::from("Unable to create buffer"))?;
+        track(|counts| counts.buffers += 1);
+        Ok(Self {
+            gl: gl.clone(),
+            buffer,
+        })
+    }
+
+    pub fn raw(&self) -> &WebGlBuffer {
+        &self.buffer
+    }
+}
+
+impl Drop for GpuBuffer {
+    fn drop(&mut self) {
+        self.gl.delete_buffer(Some(&self.buffer));
+        track(|counts| counts.buffers -= 1);
+    }
+}
+
+/// Linked shader program released together with the value owning it
+pub struct GpuProgram {
+    gl: GlContext,
+    program: WebGlProgram,
+}
+
+impl GpuProgram {
+    pub fn link(gl: &GlContext, vert_source: &str, frag_source: &str) -> Result<Self, String> {
+        let program = link_program(gl, vert_source, frag_source)?;
+        track(|counts| counts.programs += 1);
+        Ok(Self {
+            gl: gl.clone(),
+            program,
+        })
+    }
+
+    pub fn raw(&self) -> &WebGlProgram {
+        &self.program
+    }
+}
+
+impl Drop for GpuProgram {
+    fn drop(&mut self) {
+        self.gl.delete_program(Some(&self.program));
+        track(|counts| counts.programs -= 1);
+    }
+}
+
+/// WebGL2 vertex array object released together with the value owning it
+pub struct GpuVertexArray {
+    gl: WebGl2RenderingContext,
+    vertex_array: WebGlVertexArrayObject,
+}
+
+impl GpuVertexArray {
+    pub fn new(gl: &WebGl2RenderingContext) -> Result<Self, String> {
+        let vertex_array = gl
+            .create_vertex_array()
+            .ok_or_else(|| String::from("Unable to create vertex array"))?;
+        track(|counts| counts.vertex_arrays += 1);
+        Ok(Self {
+            gl: gl.clone(),
+            vertex_array,
+        })
+    }
+
+    pub fn raw(&self) -> &WebGlVertexArrayObject {
+        &self.vertex_array
+    }
+}
+
+impl Drop for GpuVertexArray {
+    fn drop(&mut self) {
+        self.gl.delete_vertex_array(Some(&self.vertex_array));
+        track(|counts| counts.vertex_arrays -= 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracking_adds_up() {
+        let before = live_resources();
+        track(|counts| counts.buffers += 2);
+        track(|counts| counts.programs += 1);
+        track(|counts| counts.buffers -= 1);
+        let after = live_resources();
+        assert_eq!(after.buffers, before.buffers + 1);
+        assert_eq!(after.programs, before.programs + 1);
+    }
+}
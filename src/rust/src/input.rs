@@ -1,5 +1,4 @@
 use crate::canvas::CanvasData;
-use crate::utils::console_log;
 use core::f32::consts::PI;
 
 #[derive(Clone, Copy, Debug)]
@@ -12,6 +11,7 @@ pub struct UserInput {
     pub rotation_x_axis: f32,
     pub rotation_y_axis: f32,
     pub spacebar: bool,
+    pub keyboard_shift: bool,
     pub keyboard_w: bool,
     pub keyboard_s: bool,
     pub keyboard_a: bool,
@@ -29,6 +29,7 @@ impl UserInput {
             mouse_x_centered: 0.,
             mouse_y_centered: 0.,
             spacebar: false,
+            keyboard_shift: false,
             keyboard_w: false,
             keyboard_s: false,
             keyboard_a: false,
@@ -45,6 +46,9 @@ impl UserInput {
     pub fn update_spacebar(&mut self, is_down: bool) {
         self.spacebar = is_down;
     }
+    pub fn update_keyboard_shift(&mut self, is_down: bool) {
+        self.keyboard_shift = is_down;
+    }
     pub fn update_keyboard_w(&mut self, is_down: bool) {
         self.keyboard_w = is_down;
     }
@@ -78,7 +82,7 @@ impl UserInput {
         self.mouse_y_centered = (cd.height / 2.) - y;
         self.mouse_x = x;
         self.mouse_y = y;
-        self.rotation_x_axis = self.rotation_x_axis + rotation_x_delta;
-        self.rotation_y_axis = self.rotation_y_axis + rotation_y_delta;
+        self.rotation_x_axis += rotation_x_delta;
+        self.rotation_y_axis += rotation_y_delta;
     }
 }
@@ -1,33 +1,74 @@
 mod client;
 mod gl_setup;
-mod programs;
+mod gpu;
+pub mod programs;
 mod shaders;
 mod utils;
 mod canvas;
 mod transform;
 mod input;
+mod timestep;
 
 use crate::input::UserInput;
 use std::fmt::Debug;
-use js_sys::Function;
 use utils::set_panic_hook;
 use wasm_bindgen::prelude::*;
 
-use web_sys::WebGlProgram;
-use web_sys::WebGlRenderingContext as GL;
 use crate::transform::Transform;
 use crate::canvas::CanvasData;
+use crate::gl_setup::{GlContext, GlVersion};
+use crate::gpu::GpuProgram;
+use crate::programs::asteroid::events::GameEvent;
 
 
 
 pub trait RenderObjectTrait {
-    fn new(gl: &GL, program: WebGlProgram, transform: Transform) -> Self where Self: Sized;
+    /// `gl` is kept to draw with later, `program` is built for `version` and
+    /// owned by the renderable, it is released when the renderable is dropped.
+    /// Fails if the renderable cannot be set up on `gl`
+    fn new(gl: &GlContext, version: GlVersion, program: GpuProgram, transform: Transform) -> Result<Self, String> where Self: Sized;
+    /// Version the renderable draws with, lower than the one it was created
+    /// for if it had to fall back
+    fn version(&self) -> Option<GlVersion> {
+        None
+    }
     fn transform(&self) -> &Transform;
     fn set_transform(&mut self, transform: Transform);
     fn input(&mut self) -> &mut UserInput;
     fn set_input(&mut self, input: UserInput);
-    fn draw_scene(&mut self, gl: &GL, canvas: &CanvasData);
-    fn update(&mut self, delta_time: f32, gl: &GL, canvas: &CanvasData, set_score: &Function);
+    /// `alpha` is the fraction of a simulation step elapsed since the last update
+    fn draw_scene(&mut self, canvas: &CanvasData, alpha: f32);
+    fn update(&mut self, delta_time: f32, canvas: &CanvasData);
+    /// Use a deterministic random source, a game in progress starts over with it
+    fn set_seed(&mut self, _seed: u64) {}
+    /// Serialized inputs of the current session, if the program records any
+    fn recording(&self) -> Option<Vec<u8>> {
+        None
+    }
+    /// Restart the session and drive it from a recording instead of live input
+    fn play_recording(&mut self, _data: &[u8]) -> Result<(), String> {
+        Err(String::from("Program does not support replays"))
+    }
+    fn is_replaying(&self) -> bool {
+        false
+    }
+    /// Serialized state of the whole program, if it supports snapshots
+    fn save_state(&self) -> Option<Vec<u8>> {
+        None
+    }
+    fn load_state(&mut self, _data: &[u8]) -> Result<(), String> {
+        Err(String::from("Program does not support snapshots"))
+    }
+    /// Game events raised since the last call
+    fn drain_events(&mut self) -> Vec<GameEvent> {
+        vec![]
+    }
+    /// Stop advancing the program until `resume` is called
+    fn pause(&mut self) {}
+    fn resume(&mut self) {}
+    /// Start over with a fresh session, keeping the GL resources that can be reused
+    /// and the seed from `set_seed`, if any
+    fn restart(&mut self) {}
 }
 
 #[wasm_bindgen]
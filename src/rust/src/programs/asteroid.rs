@@ -1,73 +1,123 @@
+pub mod backend;
+pub mod batch;
+pub mod broadphase;
+pub mod collision;
+pub mod config;
+pub mod entity;
+pub mod events;
+pub mod instancing;
+pub mod particles;
+pub mod renderer;
+pub mod replay;
 pub mod shaders;
 pub mod ship;
+pub mod simulation;
+pub mod snapshot;
+pub mod state;
 pub mod transform;
+pub mod webgl;
+pub mod webgl2;
+pub mod world;
 use crate::canvas::CanvasData;
+use crate::gl_setup::{GlContext, GlVersion};
+use crate::gpu::GpuProgram;
 use crate::input::UserInput;
-use crate::programs::asteroid::ship::Asteroid;
 use crate::transform::Transform as UserTransform;
-use crate::utils::console_log;
 use crate::RenderObjectTrait;
 use core::f32::consts::PI;
-use js_sys::Function;
-use rand::prelude::*;
-use std::collections::HashMap;
-use wasm_bindgen::JsValue;
-use web_sys::WebGlBuffer;
-use web_sys::WebGlProgram;
-use web_sys::WebGlRenderingContext as GL;
+use serde::{Deserialize, Serialize};
 
-use self::ship::{Bullet, SpaceShip};
-
-use super::box_2d::UniformLocations;
+use self::backend::RenderBackend;
+use self::events::{EventQueue, GameEvent};
+use self::particles::ParticleSystem;
+use self::renderer::Renderer;
+use self::replay::{Playback, Recording};
+use self::simulation::Simulation;
+use self::state::{GameState, StateMachine};
+use self::world::WorldBounds;
 
 const Z_OFFSET: f32 = -10.;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameObject {
     pub radius: f32,
+    /// Heading in degrees, clockwise from straight up
     pub angle: f32,
-    pub speed: f32,
+    /// Degrees per millisecond
+    pub angular_velocity: f32,
     pub position: bevy_math::Vec2,
+    /// World units per millisecond, independent of the heading
+    pub velocity: bevy_math::Vec2,
+    pub acceleration: bevy_math::Vec2,
+    pub max_speed: f32,
+    /// Fraction of the velocity lost per second
+    pub drag: f32,
+    /// State at the start of the last step, used to interpolate rendering
+    pub previous_position: bevy_math::Vec2,
+    pub previous_angle: f32,
+    /// Unit vector of the heading
     pub direction: bevy_math::Vec2,
     pub scale: bevy_math::Vec3,
     pub rotation: bevy_math::Mat3,
-    pub transformation: transform::Transform,
-
-    pub buffers: Drawable,
 }
 
 impl GameObject {
-    pub fn new(buffers: Drawable, offset_z: f32) -> Self {
-        let mut s = Self {
+    pub fn new() -> Self {
+        Self {
             radius: 0.,
             angle: 0.,
-            speed: 0.,
+            angular_velocity: 0.,
             position: bevy_math::Vec2::new(0., 0.),
+            velocity: bevy_math::Vec2::new(0., 0.),
+            acceleration: bevy_math::Vec2::new(0., 0.),
+            max_speed: f32::INFINITY,
+            drag: 0.,
+            previous_position: bevy_math::Vec2::new(0., 0.),
+            previous_angle: 0.,
             direction: bevy_math::Vec2::new(0., 1.),
             scale: bevy_math::Vec3::new(1., 1., 1.),
             rotation: bevy_math::Mat3::identity(),
-            transformation: transform::Transform::identity(),
-            buffers,
-        };
-        s
+        }
     }
 
     pub fn update(&mut self, delta_time: f32) {
+        self.previous_position = self.position;
+        self.previous_angle = self.angle;
+
+        self.angle += self.angular_velocity * delta_time;
         self.rotation = get_matrix_rotation(self.angle);
 
         // Update direction matrix
         let dir3 = self.rotation.mul_vec3(bevy_math::Vec3::new(0., 1., 0.));
         self.direction = get_vec2_from_vec3(&dir3);
 
-        // Update translation matrix
-        let velocity = self.direction * self.speed * delta_time;
-        self.position += velocity;
+        // Integrate velocity, the heading does not steer momentum
+        self.velocity += self.acceleration * delta_time;
+        if self.velocity.length_squared() > self.max_speed * self.max_speed {
+            self.velocity = self.velocity.normalize() * self.max_speed;
+        }
+        if self.drag > 0. {
+            self.velocity *= (1. - self.drag).max(0.).powf(delta_time / 1000.);
+        }
+        self.position += self.velocity * delta_time;
+    }
+
+    /// Move without interpolating from the old position, e.g. when wrapping
+    pub fn teleport(&mut self, position: bevy_math::Vec2) {
+        self.position = position;
+        self.previous_position = position;
+    }
+
+    pub fn interpolated_position(&self, alpha: f32) -> bevy_math::Vec2 {
+        self.previous_position.lerp(self.position, alpha)
+    }
+
+    pub fn interpolated_angle(&self, alpha: f32) -> f32 {
+        self.previous_angle + (self.angle - self.previous_angle) * alpha
     }
 
     pub fn does_overlap(obj1: &GameObject, obj2: &GameObject) -> bool {
-        let overlap =
-            GameObject::circles_overlap(obj1.position, obj1.radius, obj2.position, obj2.radius);
-        overlap
+        GameObject::circles_overlap(obj1.position, obj1.radius, obj2.position, obj2.radius)
     }
 
     /* Helpers */
@@ -77,24 +127,9 @@ impl GameObject {
     }
 }
 
-pub struct AttributeLocationsLocal {
-    pub vertex_position: i32,
-}
-
-#[derive(Debug, Clone)]
-pub struct Drawable {
-    pub item_size: i32,
-    pub num_items: i32,
-    pub buffer_vertices: WebGlBuffer,
-}
-
-impl Drawable {
-    pub fn new(item_size: i32, num_items: i32, buffer_vertices: WebGlBuffer) -> Self {
-        Self {
-            item_size,
-            num_items,
-            buffer_vertices,
-        }
+impl Default for GameObject {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -109,123 +144,102 @@ pub fn get_matrix_rotation(theta: f32) -> bevy_math::Mat3 {
     )
 }
 
-pub fn get_vec2_from_vec3(dir3: &bevy_math::Vec3) -> bevy_math::Vec2 {
-    dir3.truncate()
+/// Unit vector pointing along `theta` degrees
+pub fn heading(theta: f32) -> bevy_math::Vec2 {
+    get_vec2_from_vec3(&get_matrix_rotation(theta).mul_vec3(bevy_math::Vec3::new(0., 1., 0.)))
 }
-impl GameObject {
-    pub fn draw(
-        &mut self,
-        gl: &GL,
-        attribute_locations: &AttributeLocationsLocal,
-        uniform_locations: &UniformLocations,
-        projection_matrix: bevy_math::Mat4,
-        canvas: &CanvasData,
-    ) {
-        {
-            // Set vertices
-            let buffer_type = GL::FLOAT;
-            let normalize = false;
-            let stride = 0;
-            let offset = 0;
 
-            gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.buffers.buffer_vertices));
-            gl.vertex_attrib_pointer_with_i32(
-                attribute_locations.vertex_position as u32,
-                self.buffers.item_size,
-                buffer_type,
-                normalize,
-                stride,
-                offset,
-            );
-        }
-
-        //  --------- NEW --------- //
-        let theta_rad = self.angle * PI / 180.;
-        let rot = bevy_math::Quat::from_axis_angle(bevy_math::Vec3::new(0., 0., -1.), theta_rad);
-
-        // Set factual values
-        self.transformation.set_translation(bevy_math::Vec3::new(
-            self.position.x(),
-            self.position.y(),
-            Z_OFFSET,
-        ));
-        self.transformation.set_rotation(rot);
-        self.transformation.set_non_uniform_scale(self.scale);
-
-        gl.uniform_matrix4fv_with_f32_array(
-            Some(&uniform_locations.model_view_matrix),
-            false,
-            // self.model_view_matrix.as_slice(),
-            &self.transformation.value().to_cols_array(),
-        );
-        gl.uniform_matrix4fv_with_f32_array(
-            Some(&uniform_locations.projection_matrix),
-            false,
-            &projection_matrix.to_cols_array(),
-        );
-
-        let offset = 0;
-        gl.draw_arrays(GL::LINES, offset, self.buffers.num_items);
-    }
+pub fn get_vec2_from_vec3(dir3: &bevy_math::Vec3) -> bevy_math::Vec2 {
+    dir3.truncate()
 }
 
 pub struct AsteroidCanvas {
     // Game itself
-    pub ship: SpaceShip,
-    pub bullets: Vec<Bullet>,
-    pub asteroids: HashMap<u64, Asteroid>,
+    pub game: Simulation,
     pub input: UserInput,
     pub transform: UserTransform,
-    pub score: u64,
-    pub lives: u8,
-    max_asteroid_id: u64,
-    min_asteroids_alive: usize,
+    // Inputs of the running session, or of the session being played back
+    recording: Recording,
+    playback: Option<Playback>,
+    state: StateMachine,
+    /// Set by the frontend, a restart reuses it
+    seed: Option<u64>,
+    // Effects only, never fed back into the game
+    particles: ParticleSystem,
+    /// Game events already seen by the particles and state transitions, in
+    /// the order they happened, waiting to be drained
+    events: EventQueue,
     // GL
-    program: WebGlProgram,
-    attribute_locations: AttributeLocationsLocal,
-    uniform_locations: UniformLocations,
+    backend: Box<dyn RenderBackend>,
+    /// What `backend` draws with
+    version: GlVersion,
+    renderer: Renderer,
 }
 
 impl AsteroidCanvas {
-    fn update_js_values(&self, update_js: &Function) {
-        let score = JsValue::from_f64(self.score as f64);
-        let lives = JsValue::from_f64(self.lives as f64);
-        // Crash explicitly if cannot update global score
-        update_js.call2(&score, &score, &lives).unwrap();
+    fn new_session(&mut self, seed: u64) {
+        self.game = Simulation::new(seed);
+        self.recording = Recording::new(seed);
+        self.playback = None;
+        self.input = UserInput::new();
+        self.renderer.reset();
+        self.particles.clear();
+        self.events.drain();
+        self.state.restart();
+        self.queue_transitions();
+    }
+
+    /// Step the game, then let the effects catch up with it. Nobody plays the
+    /// attract mode demo, its events only feed the effects.
+    fn step(&mut self, input: &UserInput, delta_time: f32) {
+        self.game.update(input, delta_time);
+        let events = self.game.drain_events();
+        for event in &events {
+            self.particles.observe(event);
+        }
+        if self.state.state() != GameState::Attract {
+            self.events.extend(events);
+        }
+        self.particles.follow(&self.game);
+        self.particles.update(delta_time, &self.game.bounds);
+    }
+
+    /// Queue state transitions behind the game events that led to them
+    fn queue_transitions(&mut self) {
+        self.events.extend(self.state.drain_events());
     }
 }
 
 impl RenderObjectTrait for AsteroidCanvas {
-    fn new(gl: &GL, program: WebGlProgram, transform: UserTransform) -> Self
+    fn new(
+        gl: &GlContext,
+        version: GlVersion,
+        program: GpuProgram,
+        transform: UserTransform,
+    ) -> Result<Self, String>
     where
         Self: Sized,
     {
-        // Store metadata
-        let input = UserInput::new();
-        let ship = SpaceShip::new(gl, Z_OFFSET);
-
-        let attribute_locations = AttributeLocationsLocal {
-            vertex_position: gl.get_attrib_location(&program, "aVertexPosition"),
-        };
-        gl.enable_vertex_attrib_array(attribute_locations.vertex_position as u32);
-        let uniform_locations = UniformLocations {
-            projection_matrix: gl.get_uniform_location(&program, "uPMatrix").unwrap(),
-            model_view_matrix: gl.get_uniform_location(&program, "uMVMatrix").unwrap(),
-        };
-        Self {
-            min_asteroids_alive: 20,
-            lives: 3,
-            bullets: vec![],
-            asteroids: HashMap::new(),
-            max_asteroid_id: 0,
-            score: 0,
-            ship,
-            input,
+        let seed = rand::random();
+        let (mut backend, version) = webgl::create_backend(gl, version, program)?;
+        Ok(Self {
+            game: Simulation::demo(seed),
+            input: UserInput::new(),
             transform,
-            program,
-            attribute_locations,
-            uniform_locations,
-        }
+            recording: Recording::new(seed),
+            playback: None,
+            state: StateMachine::new(),
+            seed: None,
+            particles: ParticleSystem::new(),
+            events: EventQueue::new(),
+            renderer: Renderer::new(backend.as_mut()),
+            backend,
+            version,
+        })
+    }
+
+    fn version(&self) -> Option<GlVersion> {
+        Some(self.version)
     }
 
     fn input(&mut self) -> &mut UserInput {
@@ -240,237 +254,156 @@ impl RenderObjectTrait for AsteroidCanvas {
     fn set_input(&mut self, input: UserInput) {
         self.input = input;
     }
-
-    fn draw_scene(&mut self, gl: &GL, canvas: &CanvasData) {
-        gl.clear_color(0., 0., 0., 1.);
-        gl.clear_depth(1.);
-        gl.enable(GL::DEPTH_TEST);
-        gl.depth_func(GL::LEQUAL);
-        gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
-
-        let z_near: f32 = 0.1;
-        let z_far: f32 = 100.0;
-
-        /*  -------- Construct projection matrix -------- */
-        let f = 1. / (canvas.get_fov() / 2.).tan();
-        let range_inv = 1. / (z_near - z_far);
-        let projection_matrix = bevy_math::mat4(
-            bevy_math::vec4(f / canvas.get_aspect(), 0., 0., 0.),
-            bevy_math::vec4(0., f, 0., 0.),
-            bevy_math::vec4(0., 0., (z_near + z_far) * range_inv, -1.),
-            bevy_math::vec4(0., 0., z_near * z_far * range_inv * 2., 0.),
-        );
-
-        /* Draw elements */
-        self.ship.obj.draw(
-            gl,
-            &self.attribute_locations,
-            &self.uniform_locations,
-            projection_matrix,
-            &canvas,
-        );
-
-        // console_log(&format!("asteroids {:?}", &self.asteroids.len()));
-        for bullet in self.bullets.iter_mut() {
-            bullet.0.draw(
-                gl,
-                &self.attribute_locations,
-                &self.uniform_locations,
-                projection_matrix,
-                &canvas,
-            )
-        }
-        for asteroid in self.asteroids.iter_mut() {
-            asteroid.1.obj.draw(
-                gl,
-                &self.attribute_locations,
-                &self.uniform_locations,
-                projection_matrix,
-                &canvas,
-            )
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        if self.state.state() == GameState::Attract {
+            // Keep showing the demo until a game is started
+            self.game = Simulation::demo(seed);
+            self.renderer.reset();
+            self.particles.clear();
+        } else {
+            // A new seed only makes sense for a fresh session
+            self.new_session(seed);
         }
     }
-
-    fn update(&mut self, delta_time: f32, gl: &GL, canvas: &CanvasData, update_js: &Function) {
-        if self.lives > 0 {
-            /* Keyboard event capture */
-            if self.input.keyboard_a {
-                self.ship.obj.angle += -7.;
-            }
-            if self.input.keyboard_d {
-                self.ship.obj.angle += 7.;
-            }
-            if self.input.keyboard_w {
-                self.ship.obj.speed += 0.0001;
-            }
-            if self.input.keyboard_s {
-                self.ship.obj.speed -= 0.0001;
-            }
-            /* Generate bullets */
-            if self.input.spacebar {
-                if self.ship.last_shot > 300. {
-                    let mut bullet = Bullet::new(gl, Z_OFFSET);
-                    bullet.0.direction = self.ship.obj.direction;
-                    bullet.0.angle = self.ship.obj.angle;
-                    bullet.0.position = self.ship.obj.position;
-                    bullet.0.speed = 0.01;
-                    self.bullets.push(bullet);
-                    self.ship.last_shot = 0.
-                }
-            }
-        }
-        /* Generate asteroids */
-
-        let mut rng = rand::thread_rng();
-        if self.asteroids.len() < self.min_asteroids_alive {
-            const INIT_RADIUS: f32 = 1.;
-            let mut asteroid = Asteroid::new(gl, Z_OFFSET, INIT_RADIUS);
-
-            let rand_x = if rand::random() {
-                // left side
-                -8.
-            } else {
-                // right side
-                8.
-            };
-            let rand_y = if rand::random() {
-                // below
-                -5.
-            } else {
-                // above
-                5.
-            };
-            asteroid.obj.position = bevy_math::Vec2::new(rand_x, rand_y);
-            asteroid.obj.speed = rng.gen_range(0.0008, 0.0015);
-            asteroid.obj.scale = bevy_math::Vec3::new(INIT_RADIUS, INIT_RADIUS, INIT_RADIUS);
-            asteroid.obj.direction =
-                bevy_math::Vec2::new(rng.gen_range(1., 100.), rng.gen_range(1., 100.));
-            asteroid.obj.angle = rng.gen_range(0, 360) as f32;
-            self.max_asteroid_id += 1;
-
-            self.asteroids.insert(self.max_asteroid_id, asteroid);
+    fn recording(&self) -> Option<Vec<u8>> {
+        Some(self.recording.encode())
+    }
+    fn play_recording(&mut self, data: &[u8]) -> Result<(), String> {
+        let playback = Playback::new(Recording::decode(data)?);
+        self.new_session(playback.seed());
+        self.game.bounds = playback.bounds();
+        self.playback = Some(playback);
+        Ok(())
+    }
+    fn save_state(&self) -> Option<Vec<u8>> {
+        Some(snapshot::save(&self.game, &self.recording))
+    }
+    fn load_state(&mut self, data: &[u8]) -> Result<(), String> {
+        let (game, recording) = snapshot::load(data)?;
+        self.game = game;
+        self.recording = recording;
+        self.playback = None;
+        // Meshes are rebuilt from the restored outlines on the next draw
+        self.renderer.reset();
+        self.particles.clear();
+        self.state.restart();
+        self.state.follow(&self.game);
+        self.queue_transitions();
+        Ok(())
+    }
+    fn drain_events(&mut self) -> Vec<GameEvent> {
+        self.events.drain()
+    }
+    fn pause(&mut self) {
+        self.state.pause();
+        self.queue_transitions();
+    }
+    fn resume(&mut self) {
+        self.state.resume();
+        self.queue_transitions();
+    }
+    fn restart(&mut self) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        self.new_session(seed);
+    }
+    fn is_replaying(&self) -> bool {
+        match &self.playback {
+            Some(playback) => !playback.is_finished(),
+            None => false,
         }
+    }
 
-        /* Despawn objects */
-        // Bullets go out of range
-        self.bullets.retain(|el| {
-            !((el.0.position.y() / 11.).abs() + 0.6 > 1.)
-                || ((el.0.position.x() / 11.).abs() * 1.6 > 1.)
-        });
-        // Bullets interact with asteroids
-        let mut removable_bullets = vec![];
-        let mut removable_asteroids = vec![];
+    fn draw_scene(&mut self, canvas: &CanvasData, alpha: f32) {
+        self.renderer.draw(
+            self.backend.as_mut(),
+            canvas,
+            &self.game,
+            &self.particles,
+            alpha,
+        );
+    }
 
-        for bullet in &self.bullets {
-            let mut drop_bullet = false;
-            for asteroid in &self.asteroids {
-                drop_bullet = GameObject::does_overlap(&bullet.0, &asteroid.1.obj);
-                if drop_bullet {
-                    self.score += 1;
-                    self.update_js_values(&update_js);
-                    removable_asteroids.push(asteroid);
-                    break;
-                }
-            }
-            if drop_bullet {
-                removable_bullets.push(bullet as *const Bullet);
+    fn update(&mut self, delta_time: f32, canvas: &CanvasData) {
+        match self.state.state() {
+            GameState::Paused => return,
+            GameState::Attract => {
+                // Nothing to record, the demo simply fills the screen
+                self.game.bounds = WorldBounds::from_canvas(canvas);
+                self.step(&UserInput::new(), delta_time);
+                return;
             }
+            _ => {}
         }
-        self.bullets
-            .retain(|b| !removable_bullets.contains(&(b as *const Bullet)));
-
-        // Split asteroids
-        let mut destroyable_keys = vec![];
-        let mut children_asteroids = HashMap::new();
-        let local_max = self.max_asteroid_id.clone();
-        let mut iterations: u64 = 0;
-        removable_asteroids.iter().for_each(|(key, a)| {
-            if a.obj.radius > 0.3 {
-                let pieces = rng.gen_range(2, 4);
-                for _ in 0..pieces {
-                    let radius = a.obj.radius / pieces as f32;
-                    let mut asteroid = Asteroid::new(gl, Z_OFFSET, radius);
-                    // asteroid.obj.transformation = a.obj.transformation;
-                    asteroid.obj.position = a.obj.position.clone();
-                    // asteroid.obj.position *= radius;
-                    asteroid.obj.radius = radius;
-                    asteroid.obj.speed = rng.gen_range(0.001, 0.005);
-                    asteroid.obj.direction =
-                        bevy_math::Vec2::new(rng.gen_range(1., 100.), rng.gen_range(1., 100.));
-                    asteroid.obj.angle = rng.gen_range(0, 360) as f32;
-
-                    iterations += 1;
-                    let key = local_max + iterations;
-                    children_asteroids.insert(key, asteroid);
-                }
-            }
-            destroyable_keys.push(key.clone().clone());
-        });
-        self.max_asteroid_id += iterations;
-        self.asteroids.extend(children_asteroids);
-
-        // Clean up asteroids
-        let mut mutatable_lives = self.lives;
-        self.asteroids.iter().for_each(|(key, el)| {
-            // Asteroids go out of range
-            if (el.obj.position.y().abs() > 8.) || (el.obj.position.x().abs() > 11.) {
-                destroyable_keys.push(key.clone());
-            }
-            // Check overlap with player
-            if GameObject::does_overlap(&self.ship.obj, &el.obj) && self.lives > 0 {
-                destroyable_keys.push(key.clone());
-                mutatable_lives -= 1;
-            }
-        });
-
-        if self.lives != mutatable_lives {
-            self.lives = mutatable_lives;
-            self.update_js_values(&update_js);
-            if self.lives <= 0 {
-                self.ship.obj.scale = bevy_math::vec3(0., 0., 0.);
-                // TODO Remove duplicate code from children asteroid spawning
-                let mut children_asteroids = HashMap::new();
-                let radius = 0.3;
-                for _ in 0..4 {
-                    let mut asteroid = Asteroid::new(gl, Z_OFFSET, radius);
-                    // asteroid.obj.transformation = a.obj.transformation;
-                    asteroid.obj.position = self.ship.obj.position.clone();
-                    // asteroid.obj.position *= radius;
-                    asteroid.obj.radius = radius;
-                    asteroid.obj.speed = rng.gen_range(0.001, 0.005);
-                    asteroid.obj.direction =
-                        bevy_math::Vec2::new(rng.gen_range(1., 100.), rng.gen_range(1., 100.));
-                    asteroid.obj.angle = rng.gen_range(0, 360) as f32;
 
-                    iterations += 1;
-                    let key = local_max + iterations;
-                    children_asteroids.insert(key, asteroid);
+        match &mut self.playback {
+            Some(playback) => match playback.next_input() {
+                Some(input) => self.step(&input, delta_time),
+                // The run is over, hold the last frame
+                None => return,
+            },
+            None => {
+                if self.recording.ticks.is_empty() {
+                    // The world keeps the size it started with, resizing the
+                    // canvas mid-session only changes the view
+                    self.game.bounds = WorldBounds::from_canvas(canvas);
+                    self.recording.bounds = self.game.bounds;
                 }
-                self.asteroids.extend(children_asteroids);
+                self.recording.record(&self.input);
+                let input = self.input;
+                self.step(&input, delta_time);
             }
         }
+        self.state.follow(&self.game);
+        self.queue_transitions();
+    }
+}
 
-        for k in &destroyable_keys {
-            self.asteroids.remove(k);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turning_keeps_momentum() {
+        let mut obj = GameObject::new();
+        obj.velocity = bevy_math::Vec2::new(0., 0.01);
+        obj.angular_velocity = 0.5;
+        for _ in 0..60 {
+            obj.update(16.);
         }
+        // Turned around, still drifting upwards
+        assert!((obj.angle - 480.).abs() < 1e-3);
+        assert_eq!(obj.velocity, bevy_math::Vec2::new(0., 0.01));
+    }
 
-        /* Position updates */
-        self.ship.update(delta_time);
+    #[test]
+    fn thrust_is_capped_by_max_speed() {
+        let mut obj = GameObject::new();
+        obj.max_speed = 0.02;
+        obj.acceleration = heading(90.) * 0.001;
+        for _ in 0..100 {
+            obj.update(16.);
+        }
+        assert!((obj.velocity.length() - 0.02).abs() < 1e-6);
+        assert!(obj.velocity.x() > 0.);
+    }
 
-        for bullet in self.bullets.iter_mut() {
-            bullet.update(delta_time);
+    #[test]
+    fn drag_does_not_depend_on_step_size() {
+        let mut fine = GameObject::new();
+        let mut coarse = GameObject::new();
+        for obj in [&mut fine, &mut coarse].iter_mut() {
+            obj.velocity = bevy_math::Vec2::new(0.01, 0.);
+            obj.drag = 0.5;
         }
-        for asteroid in self.asteroids.iter_mut() {
-            asteroid.1.update(delta_time);
+        for _ in 0..100 {
+            fine.update(10.);
         }
-
-        /* Increase difficulty */
-        if (10..20).contains(&self.score) {
-            self.min_asteroids_alive = 30;
-        } else if (40..50).contains(&self.score) {
-            self.min_asteroids_alive = 50;
-        } else if (100..200).contains(&self.score) {
-            self.min_asteroids_alive = 70;
+        for _ in 0..10 {
+            coarse.update(100.);
         }
+        // Half the velocity is gone after one second either way
+        assert!((fine.velocity.x() - 0.005).abs() < 1e-6);
+        assert!((coarse.velocity.x() - 0.005).abs() < 1e-6);
     }
 }
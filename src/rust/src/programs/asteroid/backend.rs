@@ -0,0 +1,58 @@
+use bevy_math::Mat4;
+
+/// Buffer owned by a backend, only meaningful to the backend that made it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferUsage {
+    /// Written once, e.g. a mesh
+    Static,
+    /// Rewritten every frame
+    Stream,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Program {
+    /// Line lists with `batch::VERTEX_SIZE` components per vertex, already in
+    /// world space
+    Lines,
+    /// Meshes with 3 components per vertex, placed by per-instance data laid
+    /// out as in `instancing::InstanceBatch`
+    Instanced,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Uniform {
+    Projection,
+    View,
+}
+
+/// What the asteroid renderer needs from the GPU. Implemented for WebGL in
+/// the browser, the renderer tests record the calls instead. Only the asteroid
+/// program draws through it, the cube and box demos still call the context
+/// directly.
+pub trait RenderBackend {
+    /// Whether `Program::Instanced` and `draw_instanced` may be used
+    fn supports_instancing(&self) -> bool;
+    /// Room for `capacity` floats, `write_buffer` fills it
+    fn create_buffer(&mut self, usage: BufferUsage, capacity: usize) -> BufferId;
+    /// Replace the contents of `buffer`, growing it if `data` does not fit
+    fn write_buffer(&mut self, buffer: BufferId, data: &[f32]);
+    fn use_program(&mut self, program: Program);
+    /// Uniforms are shared by every program, an upload holds until the next
+    fn upload_uniform(&mut self, uniform: Uniform, value: &Mat4);
+    fn clear(&mut self);
+    /// Draw `count` line vertices of `buffer` from vertex `first` on
+    fn draw_lines(&mut self, buffer: BufferId, first: usize, count: usize);
+    /// Draw the `vertices` of `mesh` once for each of the `count` instances
+    /// of `instances`, from instance `first` on
+    fn draw_instanced(
+        &mut self,
+        mesh: BufferId,
+        vertices: usize,
+        instances: BufferId,
+        first: usize,
+        count: usize,
+    );
+}
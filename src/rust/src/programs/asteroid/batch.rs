@@ -0,0 +1,85 @@
+use bevy_math::{Mat4, Vec3};
+
+/// Components per batched vertex: position and brightness, the layout the
+/// asteroid shaders expect
+pub const VERTEX_SIZE: usize = 4;
+
+/// Line list of a whole frame. Meshes are moved into place on the CPU, so the
+/// scene goes to the GPU in a single upload and a single draw call.
+#[derive(Debug, Clone, Default)]
+pub struct LineBatch {
+    vertices: Vec<f32>,
+}
+
+impl LineBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(vertices: usize) -> Self {
+        Self {
+            vertices: Vec::with_capacity(vertices * VERTEX_SIZE),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Vertices batched so far
+    pub fn len(&self) -> usize {
+        self.vertices.len() / VERTEX_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn vertices(&self) -> &[f32] {
+        &self.vertices
+    }
+
+    /// Add a line list with 3 components per vertex, placed by `transform`
+    pub fn push_mesh(&mut self, mesh: &[f32], transform: &Mat4, brightness: f32) {
+        for vertex in mesh.chunks_exact(3) {
+            let world = transform.transform_point3(Vec3::new(vertex[0], vertex[1], vertex[2]));
+            self.vertices
+                .extend_from_slice(&[world.x(), world.y(), world.z(), brightness]);
+        }
+    }
+
+    /// Room to append vertices that are already in place, `VERTEX_SIZE`
+    /// components each
+    pub fn raw(&mut self) -> &mut Vec<f32> {
+        &mut self.vertices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_math::Quat;
+
+    #[test]
+    fn meshes_are_moved_into_place() {
+        let mut batch = LineBatch::new();
+        let mesh = [0., 1., 0., 0., -1., 0.];
+        let transform = Mat4::from_rotation_translation(
+            Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+            Vec3::new(2., 0., -10.),
+        );
+        batch.push_mesh(&mesh, &transform, 0.5);
+        batch.push_mesh(&mesh, &Mat4::identity(), 1.);
+        assert_eq!(batch.len(), 4);
+
+        let first = &batch.vertices()[..VERTEX_SIZE];
+        assert!((first[0] - 1.).abs() < 1e-6);
+        assert!(first[1].abs() < 1e-6);
+        assert_eq!(first[2], -10.);
+        assert_eq!(first[3], 0.5);
+        assert_eq!(&batch.vertices()[8..12], &[0., 1., 0., 1.]);
+
+        batch.clear();
+        assert!(batch.is_empty());
+    }
+}
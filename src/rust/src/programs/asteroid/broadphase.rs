@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+use bevy_math::Vec2;
+
+use crate::programs::asteroid::entity::Handle;
+use crate::programs::asteroid::world::WorldBounds;
+
+/// Uniform grid used to narrow collision pairs before the exact overlap test.
+///
+/// Objects are bucketed into every cell their bounding circle touches, a query
+/// returns the objects sharing a cell with the probing circle. Candidates come back
+/// sorted by handle, the same order the entity store iterates in, so picking
+/// the first overlapping candidate matches a brute-force scan of the store.
+#[derive(Debug, Clone)]
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<Handle>, BuildHasherDefault<CellHasher>>,
+}
+
+impl SpatialHash {
+    /// Cells should be about as large as the biggest object that is inserted
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+        }
+    }
+
+    /// Empty the grid, keeping the allocated buckets around for the next tick
+    pub fn clear(&mut self) {
+        for bucket in self.cells.values_mut() {
+            bucket.clear();
+        }
+    }
+
+    pub fn insert(&mut self, handle: Handle, position: Vec2, radius: f32) {
+        let (min, max) = self.cell_range(position, radius);
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                self.cells.entry((x, y)).or_default().push(handle);
+            }
+        }
+    }
+
+    /// Insert the object along with its copies across the edges of the world
+    pub fn insert_wrapped(
+        &mut self,
+        handle: Handle,
+        position: Vec2,
+        radius: f32,
+        bounds: &WorldBounds,
+    ) {
+        for image in bounds.images(position, radius) {
+            self.insert(handle, image, radius);
+        }
+    }
+
+    /// Handles whose cells overlap with the circle, sorted and without duplicates
+    pub fn query(&self, position: Vec2, radius: f32, candidates: &mut Vec<Handle>) {
+        candidates.clear();
+        self.collect(position, radius, candidates);
+        candidates.sort_unstable();
+        candidates.dedup();
+    }
+
+    /// Like `query`, but also looks past the edges of the world the circle crosses
+    pub fn query_wrapped(
+        &self,
+        position: Vec2,
+        radius: f32,
+        bounds: &WorldBounds,
+        candidates: &mut Vec<Handle>,
+    ) {
+        candidates.clear();
+        for image in bounds.images(position, radius) {
+            self.collect(image, radius, candidates);
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+    }
+
+    fn collect(&self, position: Vec2, radius: f32, candidates: &mut Vec<Handle>) {
+        let (min, max) = self.cell_range(position, radius);
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                if let Some(bucket) = self.cells.get(&(x, y)) {
+                    candidates.extend_from_slice(bucket);
+                }
+            }
+        }
+    }
+
+    fn cell_range(&self, position: Vec2, radius: f32) -> ((i32, i32), (i32, i32)) {
+        let cell = |value: f32| (value / self.cell_size).floor() as i32;
+        let radius = radius.abs();
+        (
+            (cell(position.x() - radius), cell(position.y() - radius)),
+            (cell(position.x() + radius), cell(position.y() + radius)),
+        )
+    }
+}
+
+/// Multiplicative hash for cell coordinates. The keys are tiny and not picked
+/// by an attacker, the std SipHash would dominate the time spent in the grid.
+#[derive(Default)]
+struct CellHasher(u64);
+
+impl Hasher for CellHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_i32(&mut self, value: i32) {
+        self.write_u64(value as u32 as u64);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+}
+
+impl Default for SpatialHash {
+    fn default() -> Self {
+        // Fits the largest asteroids spawned by the game
+        Self::new(2.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::asteroid::entity::EntityStore;
+    use crate::programs::asteroid::GameObject;
+    use rand::prelude::*;
+    use rand_pcg::Pcg32;
+
+    fn object(x: f32, y: f32, radius: f32) -> GameObject {
+        let mut obj = GameObject::new();
+        obj.position = bevy_math::Vec2::new(x, y);
+        obj.radius = radius;
+        obj
+    }
+
+    #[test]
+    fn finds_the_same_pairs_as_brute_force() {
+        let mut rng = Pcg32::seed_from_u64(11);
+        let mut store = EntityStore::new();
+        for _ in 0..500 {
+            store.spawn(object(
+                rng.gen_range(-11., 11.),
+                rng.gen_range(-8., 8.),
+                rng.gen_range(0.05, 1.),
+            ));
+        }
+        let mut grid = SpatialHash::default();
+        for (handle, obj) in store.iter() {
+            grid.insert(handle, obj.position, obj.radius);
+        }
+
+        let mut candidates = vec![];
+        for _ in 0..200 {
+            let probe = object(rng.gen_range(-12., 12.), rng.gen_range(-9., 9.), 0.);
+            let expected: Vec<Handle> = store
+                .iter()
+                .filter(|(_, obj)| GameObject::does_overlap(&probe, obj))
+                .map(|(handle, _)| handle)
+                .collect();
+            grid.query(probe.position, probe.radius, &mut candidates);
+            let found: Vec<Handle> = candidates
+                .iter()
+                .copied()
+                .filter(|handle| GameObject::does_overlap(&probe, store.get(*handle).unwrap()))
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn finds_neighbours_across_edges() {
+        let bounds = WorldBounds::new(5., 3.);
+        let mut store = EntityStore::new();
+        let left = store.spawn(object(-4.9, 0., 0.5));
+        let top = store.spawn(object(0., 2.9, 0.2));
+        let mut grid = SpatialHash::default();
+        for (handle, obj) in store.iter() {
+            grid.insert_wrapped(handle, obj.position, obj.radius, &bounds);
+        }
+
+        let mut candidates = vec![];
+        // Sticks out of the right edge, reaching the asteroid on the left
+        grid.query_wrapped(bevy_math::Vec2::new(4.8, 0.), 0.1, &bounds, &mut candidates);
+        assert_eq!(candidates, vec![left]);
+        // Only the asteroid sticks out of the edge
+        grid.query_wrapped(bevy_math::Vec2::new(0., -2.9), 0., &bounds, &mut candidates);
+        assert_eq!(candidates, vec![top]);
+    }
+
+    #[test]
+    fn clear_forgets_inserted_objects() {
+        let mut store = EntityStore::new();
+        let handle = store.spawn(object(0., 0., 1.));
+        let mut grid = SpatialHash::default();
+        let obj = store.get(handle).unwrap();
+        grid.insert(handle, obj.position, obj.radius);
+
+        let mut candidates = vec![];
+        let probe = bevy_math::Vec2::new(0.5, 0.5);
+        grid.query(probe, 0., &mut candidates);
+        assert_eq!(candidates, vec![handle]);
+        grid.clear();
+        grid.query(probe, 0., &mut candidates);
+        assert!(candidates.is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+use core::f32::consts::PI;
+
+use bevy_math::Vec2;
+
+use crate::programs::asteroid::world::WorldBounds;
+use crate::programs::asteroid::GameObject;
+
+/// Place object space points into the world the way the renderer draws them:
+/// scaled, turned clockwise by the object's angle and moved to its position
+pub fn to_world<'a, I>(obj: &GameObject, points: I) -> Vec<Vec2>
+where
+    I: IntoIterator<Item = &'a (f32, f32, f32)>,
+{
+    let theta = obj.angle * PI / 180.;
+    let (sin, cos) = theta.sin_cos();
+    points
+        .into_iter()
+        .map(|point| {
+            let x = point.0 * obj.scale.x();
+            let y = point.1 * obj.scale.y();
+            obj.position + Vec2::new(x * cos + y * sin, y * cos - x * sin)
+        })
+        .collect()
+}
+
+/// Radius of the smallest circle around the object's origin holding all points
+pub fn bounding_radius<'a, I>(obj: &GameObject, points: I) -> f32
+where
+    I: IntoIterator<Item = &'a (f32, f32, f32)>,
+{
+    let scale = obj.scale.x().abs().max(obj.scale.y().abs());
+    points
+        .into_iter()
+        .map(|point| Vec2::new(point.0, point.1).length())
+        .fold(0., f32::max)
+        * scale
+}
+
+/// An object's outline as the narrow phase sees it. The world placed outline is
+/// only computed once the cheaper bounding circle test passes.
+#[derive(Clone, Copy)]
+pub struct Hull<'a> {
+    obj: &'a GameObject,
+    points: &'a [(f32, f32, f32)],
+}
+
+impl<'a> Hull<'a> {
+    pub fn new(obj: &'a GameObject, points: &'a [(f32, f32, f32)]) -> Self {
+        Self { obj, points }
+    }
+
+    pub fn position(&self) -> Vec2 {
+        self.obj.position
+    }
+
+    pub fn bounding_radius(&self) -> f32 {
+        bounding_radius(self.obj, self.points)
+    }
+
+    /// Outline as currently placed in the world
+    pub fn outline(&self) -> Vec<Vec2> {
+        to_world(self.obj, self.points)
+    }
+
+    /// Whether a bullet trail touches the outline. The trail is moved next to
+    /// the object if they are apart across an edge.
+    pub fn is_hit_by(&self, start: Vec2, end: Vec2, bounds: &WorldBounds) -> bool {
+        let shift = self.image_shift((start + end) / 2., bounds);
+        let (start, end) = (start + shift, end + shift);
+        distance_to_segment(self.position(), start, end) <= self.bounding_radius()
+            && segment_hits_polygon(start, end, &self.outline())
+    }
+
+    /// Whether two outlines overlap. The other one is moved next to this one
+    /// if they are apart across an edge.
+    pub fn overlaps(&self, other: &Hull<'_>, bounds: &WorldBounds) -> bool {
+        let shift = self.image_shift(other.position(), bounds);
+        let reach = self.bounding_radius() + other.bounding_radius();
+        (other.position() + shift - self.position()).length_squared() <= reach * reach && {
+            let outline: Vec<Vec2> = other
+                .outline()
+                .into_iter()
+                .map(|point| point + shift)
+                .collect();
+            polygons_overlap(&self.outline(), &outline)
+        }
+    }
+
+    /// Offset moving `point` to its copy closest to the object
+    fn image_shift(&self, point: Vec2, bounds: &WorldBounds) -> Vec2 {
+        self.position() - bounds.delta(point, self.position()) - point
+    }
+}
+
+/// Whether the segment `a`-`b` touches the closed polygon, inside or on its edges
+pub fn segment_hits_polygon(a: Vec2, b: Vec2, polygon: &[Vec2]) -> bool {
+    contains(polygon, a) || edges(polygon).any(|(p, q)| segments_intersect(a, b, p, q))
+}
+
+/// Whether two closed polygons overlap. Works for concave outlines too, as
+/// either an edge of one crosses the other or one lies completely inside it.
+pub fn polygons_overlap(first: &[Vec2], second: &[Vec2]) -> bool {
+    if first.is_empty() || second.is_empty() {
+        return false;
+    }
+    contains(first, second[0])
+        || contains(second, first[0])
+        || edges(first).any(|(a, b)| edges(second).any(|(p, q)| segments_intersect(a, b, p, q)))
+}
+
+/// Point in polygon by the even-odd rule
+pub fn contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (a, b) in edges(polygon) {
+        if (a.y() > point.y()) != (b.y() > point.y()) {
+            let cross_x = a.x() + (point.y() - a.y()) / (b.y() - a.y()) * (b.x() - a.x());
+            if point.x() < cross_x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    if length_squared == 0. {
+        return (point - start).length();
+    }
+    let t = ((point - start).dot(segment) / length_squared).clamp(0., 1.);
+    (point - (start + segment * t)).length()
+}
+
+fn edges(polygon: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+fn segments_intersect(a: Vec2, b: Vec2, p: Vec2, q: Vec2) -> bool {
+    let d1 = orientation(p, q, a);
+    let d2 = orientation(p, q, b);
+    let d3 = orientation(a, b, p);
+    let d4 = orientation(a, b, q);
+    if ((d1 > 0.) != (d2 > 0.) && d1 != 0. && d2 != 0.)
+        && ((d3 > 0.) != (d4 > 0.) && d3 != 0. && d4 != 0.)
+    {
+        return true;
+    }
+    // Touching or collinear
+    (d1 == 0. && on_segment(p, q, a))
+        || (d2 == 0. && on_segment(p, q, b))
+        || (d3 == 0. && on_segment(a, b, p))
+        || (d4 == 0. && on_segment(a, b, q))
+}
+
+fn orientation(a: Vec2, b: Vec2, c: Vec2) -> f32 {
+    let ab = b - a;
+    let ac = c - a;
+    ab.x() * ac.y() - ab.y() * ac.x()
+}
+
+fn on_segment(a: Vec2, b: Vec2, point: Vec2) -> bool {
+    point.x() >= a.x().min(b.x())
+        && point.x() <= a.x().max(b.x())
+        && point.y() >= a.y().min(b.y())
+        && point.y() <= a.y().max(b.y())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A "C" shape, the notch on the right reaches past the centre
+    const NOTCHED: [(f32, f32, f32); 8] = [
+        (-1., -1., 0.),
+        (1., -1., 0.),
+        (1., -0.2, 0.),
+        (-0.5, -0.2, 0.),
+        (-0.5, 0.2, 0.),
+        (1., 0.2, 0.),
+        (1., 1., 0.),
+        (-1., 1., 0.),
+    ];
+
+    #[test]
+    fn segments_in_the_notch_miss() {
+        let polygon = to_world(&GameObject::new(), NOTCHED.iter());
+        // Inside the bounding circle, but in empty space
+        assert!(!segment_hits_polygon(
+            Vec2::new(0.2, 0.),
+            Vec2::new(0.8, 0.),
+            &polygon
+        ));
+        // Fully inside the solid part
+        assert!(segment_hits_polygon(
+            Vec2::new(-0.9, 0.),
+            Vec2::new(-0.8, 0.),
+            &polygon
+        ));
+        // Crossing an edge from the outside
+        assert!(segment_hits_polygon(
+            Vec2::new(0.5, 2.),
+            Vec2::new(0.5, 0.5),
+            &polygon
+        ));
+    }
+
+    #[test]
+    fn rotation_and_scale_move_the_outline() {
+        let mut obj = GameObject::new();
+        let probe = [Vec2::new(0.2, 0.), Vec2::new(0.8, 0.)];
+        assert!(!segment_hits_polygon(
+            probe[0],
+            probe[1],
+            &to_world(&obj, NOTCHED.iter())
+        ));
+
+        // A quarter turn clockwise moves the notch downwards, closing the gap
+        obj.angle = 90.;
+        assert!(segment_hits_polygon(
+            probe[0],
+            probe[1],
+            &to_world(&obj, NOTCHED.iter())
+        ));
+
+        // Shrunk far enough the outline no longer reaches the probe
+        obj.scale = bevy_math::vec3(0.1, 0.1, 1.);
+        assert!(!segment_hits_polygon(
+            probe[0],
+            probe[1],
+            &to_world(&obj, NOTCHED.iter())
+        ));
+        assert!((bounding_radius(&obj, NOTCHED.iter()) - 0.1 * 2f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn polygons_overlap_by_crossing_or_containment() {
+        let notched = to_world(&GameObject::new(), NOTCHED.iter());
+        let small = |x: f32, y: f32| {
+            vec![
+                Vec2::new(x - 0.05, y - 0.05),
+                Vec2::new(x + 0.05, y - 0.05),
+                Vec2::new(x, y + 0.05),
+            ]
+        };
+        assert!(polygons_overlap(&notched, &small(-0.8, 0.)));
+        assert!(polygons_overlap(&small(-0.8, 0.), &notched));
+        assert!(polygons_overlap(&notched, &small(1., 0.5)));
+        assert!(!polygons_overlap(&notched, &small(0.5, 0.)));
+        assert!(!polygons_overlap(&notched, &small(3., 3.)));
+    }
+}
@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+
+use crate::programs::asteroid::ship::{AsteroidSize, SaucerSize};
+
+/// Tunable rules of a session. Rates are per millisecond, matching the
+/// `delta_time` the simulation is stepped with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct GameConfig {
+    pub ship: ShipConfig,
+    pub bullet: BulletConfig,
+    pub hyperspace: HyperspaceConfig,
+    pub saucer: SaucerConfig,
+    pub wave: WaveConfig,
+    pub respawn: RespawnConfig,
+    pub scoring: ScoringConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShipConfig {
+    /// Acceleration along the heading while thrusting
+    pub thrust: f32,
+    /// Degrees turned per millisecond while steering
+    pub turn_rate: f32,
+    pub max_speed: f32,
+    /// Fraction of the velocity lost over one second of coasting
+    pub drag: f32,
+}
+
+impl Default for ShipConfig {
+    fn default() -> Self {
+        // Tuned to feel like the original per-frame constants at 60Hz
+        Self {
+            thrust: 0.000_006,
+            turn_rate: 0.42,
+            max_speed: 0.01,
+            drag: 0.45,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BulletConfig {
+    /// Speed relative to the ship firing it
+    pub speed: f32,
+    /// Milliseconds until a bullet fizzles out
+    pub time_to_live: f32,
+    /// Distance after which a bullet fizzles out, whichever comes first
+    pub range: f32,
+    /// Add the ship's velocity to the bullet, shots fired while drifting go further
+    pub inherit_velocity: bool,
+    /// Milliseconds between two shots
+    pub cooldown: f32,
+    /// Bullets a ship can have in flight at once
+    pub max_alive: usize,
+}
+
+impl Default for BulletConfig {
+    fn default() -> Self {
+        Self {
+            speed: 0.01,
+            time_to_live: 800.,
+            range: 8.,
+            inherit_velocity: true,
+            cooldown: 300.,
+            max_alive: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HyperspaceConfig {
+    /// Milliseconds between two jumps
+    pub cooldown: f32,
+    /// Odds of the ship blowing up on re-entry, from 0 to 1
+    pub self_destruct_chance: f32,
+}
+
+impl Default for HyperspaceConfig {
+    fn default() -> Self {
+        Self {
+            cooldown: 2000.,
+            self_destruct_chance: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaucerConfig {
+    /// Milliseconds without a saucer before the next one shows up
+    pub spawn_interval: f32,
+    /// Odds of the next saucer being a small one, from 0 to 1
+    pub small_chance: f32,
+    pub speed: f32,
+    /// Milliseconds between two zig-zag turns
+    pub turn_interval: f32,
+    /// Milliseconds between two shots
+    pub cooldown: f32,
+    pub bullet_speed: f32,
+    /// Degrees the small saucer's aim is off by at most, with no score yet
+    pub aim_error: f32,
+    /// Degrees the aim is off by at most once the score reaches `sharpshooter_score`
+    pub min_aim_error: f32,
+    pub sharpshooter_score: u64,
+}
+
+impl Default for SaucerConfig {
+    fn default() -> Self {
+        Self {
+            spawn_interval: 10_000.,
+            small_chance: 0.3,
+            speed: 0.0025,
+            turn_interval: 1200.,
+            cooldown: 1000.,
+            bullet_speed: 0.006,
+            aim_error: 30.,
+            min_aim_error: 2.,
+            sharpshooter_score: 20_000,
+        }
+    }
+}
+
+/// Each wave brings in a fresh set of large asteroids, a couple more and a
+/// bit faster than the one before
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaveConfig {
+    /// Large asteroids in the first wave
+    pub first_asteroids: usize,
+    /// Asteroids added with every following wave
+    pub extra_asteroids: usize,
+    pub max_asteroids: usize,
+    /// Fraction the asteroid speed grows by with every following wave
+    pub speed_increase: f32,
+    /// Milliseconds between clearing a wave and the next one coming in
+    pub pause: f32,
+}
+
+impl WaveConfig {
+    /// Large asteroids spawned by wave number `wave`, counting from 1
+    pub fn asteroids(&self, wave: u32) -> usize {
+        let extra = self.extra_asteroids * wave.saturating_sub(1) as usize;
+        (self.first_asteroids + extra).min(self.max_asteroids)
+    }
+
+    /// Multiplier of the asteroid speed in wave number `wave`
+    pub fn speed_factor(&self, wave: u32) -> f32 {
+        1. + self.speed_increase * wave.saturating_sub(1) as f32
+    }
+}
+
+impl Default for WaveConfig {
+    fn default() -> Self {
+        Self {
+            first_asteroids: 4,
+            extra_asteroids: 2,
+            max_asteroids: 11,
+            speed_increase: 0.1,
+            pause: 2000.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RespawnConfig {
+    /// Milliseconds before a lost ship may come back
+    pub delay: f32,
+    /// The ship waits for the centre to be clear of asteroids by this distance
+    pub safe_radius: f32,
+    /// Milliseconds nothing can hit the ship after it came back
+    pub invulnerability: f32,
+    /// Milliseconds the ship is shown or hidden for while invulnerable
+    pub blink_interval: f32,
+}
+
+impl Default for RespawnConfig {
+    fn default() -> Self {
+        Self {
+            delay: 1000.,
+            safe_radius: 2.,
+            invulnerability: 3000.,
+            blink_interval: 150.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoringConfig {
+    pub large_asteroid: u64,
+    pub medium_asteroid: u64,
+    pub small_asteroid: u64,
+    pub large_saucer: u64,
+    pub small_saucer: u64,
+    /// A life is added every time the score passes a multiple of this, 0 never
+    pub extra_life_every: u64,
+    /// Extra lives stop adding up here
+    pub max_lives: u8,
+    /// Chain hits to multiply the points they are worth
+    pub combo: bool,
+    /// Added to the multiplier by every hit
+    pub combo_step: f32,
+    pub max_combo: f32,
+    /// Multiplier lost over one second, it never drops below 1
+    pub combo_decay: f32,
+}
+
+impl ScoringConfig {
+    pub fn asteroid(&self, size: AsteroidSize) -> u64 {
+        match size {
+            AsteroidSize::Large => self.large_asteroid,
+            AsteroidSize::Medium => self.medium_asteroid,
+            AsteroidSize::Small => self.small_asteroid,
+        }
+    }
+
+    pub fn saucer(&self, size: SaucerSize) -> u64 {
+        match size {
+            SaucerSize::Large => self.large_saucer,
+            SaucerSize::Small => self.small_saucer,
+        }
+    }
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        // The smaller the target, the harder it is to hit
+        Self {
+            large_asteroid: 20,
+            medium_asteroid: 50,
+            small_asteroid: 100,
+            large_saucer: 200,
+            small_saucer: 1000,
+            extra_life_every: 10_000,
+            max_lives: 9,
+            combo: true,
+            combo_step: 0.25,
+            max_combo: 3.,
+            combo_decay: 1.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waves_grow_up_to_the_limit() {
+        let config = WaveConfig::default();
+        assert_eq!(config.asteroids(1), 4);
+        assert_eq!(config.asteroids(2), 6);
+        assert_eq!(config.asteroids(50), 11);
+        assert_eq!(config.speed_factor(1), 1.);
+        assert!((config.speed_factor(3) - 1.2).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+
+/// Stable reference to an entity in an `EntityStore`.
+///
+/// The generation changes every time a slot is reused, so a handle to a
+/// despawned entity never resolves to whatever took its place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Slot<T> {
+    Occupied {
+        generation: u32,
+        value: T,
+    },
+    Free {
+        generation: u32,
+        next_free: Option<u32>,
+    },
+}
+
+/// Generational arena holding one kind of entity.
+///
+/// Spawning and despawning are O(1), freed slots are reused last-in first-out
+/// and iteration always walks the slots in index order, so two stores fed the
+/// same operations iterate identically.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityStore<T> {
+    slots: Vec<Slot<T>>,
+    free_head: Option<u32>,
+    len: usize,
+}
+
+impl<T> EntityStore<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![],
+            free_head: None,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn spawn(&mut self, value: T) -> Handle {
+        self.len += 1;
+        match self.free_head {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                let (generation, next_free) = match slot {
+                    Slot::Free {
+                        generation,
+                        next_free,
+                    } => (*generation, *next_free),
+                    Slot::Occupied { .. } => unreachable!("free list points at a live entity"),
+                };
+                *slot = Slot::Occupied { generation, value };
+                self.free_head = next_free;
+                Handle { index, generation }
+            }
+            None => {
+                let index = self.slots.len() as u32;
+                self.slots.push(Slot::Occupied {
+                    generation: 0,
+                    value,
+                });
+                Handle {
+                    index,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub fn despawn(&mut self, handle: Handle) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        match slot {
+            Slot::Occupied { generation, .. } if *generation == handle.generation => {
+                let freed = Slot::Free {
+                    generation: generation.wrapping_add(1),
+                    next_free: self.free_head,
+                };
+                self.free_head = Some(handle.index);
+                self.len -= 1;
+                match std::mem::replace(slot, freed) {
+                    Slot::Occupied { value, .. } => Some(value),
+                    Slot::Free { .. } => unreachable!(),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        match self.slots.get(handle.index as usize)? {
+            Slot::Occupied { generation, value } if *generation == handle.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        match self.slots.get_mut(handle.index as usize)? {
+            Slot::Occupied { generation, value } if *generation == handle.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| match slot {
+                Slot::Occupied { generation, value } => Some((
+                    Handle {
+                        index: index as u32,
+                        generation: *generation,
+                    },
+                    value,
+                )),
+                Slot::Free { .. } => None,
+            })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| match slot {
+                Slot::Occupied { generation, value } => Some((
+                    Handle {
+                        index: index as u32,
+                        generation: *generation,
+                    },
+                    value,
+                )),
+                Slot::Free { .. } => None,
+            })
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.iter_mut().map(|(_, value)| value)
+    }
+
+    pub fn handles(&self) -> Vec<Handle> {
+        self.iter().map(|(handle, _)| handle).collect()
+    }
+
+    /// Despawn every entity for which `keep` returns false
+    pub fn retain<F: FnMut(Handle, &mut T) -> bool>(&mut self, mut keep: F) {
+        let doomed: Vec<Handle> = self
+            .iter_mut()
+            .filter_map(|(handle, value)| {
+                if keep(handle, value) {
+                    None
+                } else {
+                    Some(handle)
+                }
+            })
+            .collect();
+        for handle in doomed {
+            self.despawn(handle);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.free_head = None;
+        self.len = 0;
+    }
+}
+
+impl<T> Default for EntityStore<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_handles_do_not_resolve() {
+        let mut store = EntityStore::new();
+        let first = store.spawn("first");
+        assert_eq!(store.despawn(first), Some("first"));
+        let second = store.spawn("second");
+
+        // The slot was reused, but the old handle stays dead
+        assert_eq!(store.get(first), None);
+        assert_eq!(store.despawn(first), None);
+        assert_eq!(store.get(second), Some(&"second"));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn iterates_in_slot_order() {
+        let mut store = EntityStore::new();
+        let handles: Vec<Handle> = (0..5).map(|i| store.spawn(i)).collect();
+        store.despawn(handles[1]);
+        store.despawn(handles[3]);
+        // Reuses slot 3 first, then slot 1
+        store.spawn(30);
+        store.spawn(10);
+
+        assert_eq!(
+            store.values().copied().collect::<Vec<_>>(),
+            vec![0, 10, 2, 30, 4]
+        );
+    }
+
+    #[test]
+    fn retain_despawns_rejected() {
+        let mut store = EntityStore::new();
+        for i in 0..10 {
+            store.spawn(i);
+        }
+        store.retain(|_, value| *value % 2 == 0);
+        assert_eq!(store.len(), 5);
+        assert!(store.values().all(|value| value % 2 == 0));
+    }
+}
@@ -0,0 +1,193 @@
+use bevy_math::Vec2;
+use js_sys::{Object, Reflect};
+use wasm_bindgen::JsValue;
+
+use crate::programs::asteroid::ship::{AsteroidSize, SaucerSize};
+use crate::programs::asteroid::state::GameState;
+
+/// Something happened in the game the frontend may want to react to, e.g.
+/// with a sound or a visual effect
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    /// The ship jumped through hyperspace, `destroyed` if it did not survive
+    Hyperspace {
+        from: Vec2,
+        to: Vec2,
+        destroyed: bool,
+    },
+    /// The asteroids of wave number `wave`, counting from 1, came in
+    WaveStarted {
+        wave: u32,
+    },
+    StateChanged {
+        from: GameState,
+        to: GameState,
+    },
+    /// Fired by the player, or by a saucer when `hostile`
+    ShotFired {
+        position: Vec2,
+        hostile: bool,
+    },
+    AsteroidDestroyed {
+        size: AsteroidSize,
+        position: Vec2,
+    },
+    SaucerDestroyed {
+        size: SaucerSize,
+        position: Vec2,
+    },
+    /// The player lost a ship, `lives` are left
+    ShipDestroyed {
+        position: Vec2,
+        lives: u8,
+    },
+    /// Points were awarded, `gained` of them, totalling `score`
+    ScoreChanged {
+        score: u64,
+        gained: u64,
+    },
+    LifeGained {
+        lives: u8,
+    },
+    /// The last ship was lost
+    GameOver {
+        score: u64,
+    },
+}
+
+impl GameEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameEvent::Hyperspace { .. } => "hyperspace",
+            GameEvent::WaveStarted { .. } => "wave",
+            GameEvent::StateChanged { .. } => "state",
+            GameEvent::ShotFired { .. } => "shot_fired",
+            GameEvent::AsteroidDestroyed { .. } => "asteroid_destroyed",
+            GameEvent::SaucerDestroyed { .. } => "saucer_destroyed",
+            GameEvent::ShipDestroyed { .. } => "ship_destroyed",
+            GameEvent::ScoreChanged { .. } => "score",
+            GameEvent::LifeGained { .. } => "life_gained",
+            GameEvent::GameOver { .. } => "game_over",
+        }
+    }
+
+    /// Plain JS object with the event name under `type`
+    pub fn to_js(&self) -> JsValue {
+        let obj = Object::new();
+        let set = |key: &str, value: JsValue| {
+            Reflect::set(&obj, &JsValue::from_str(key), &value).unwrap();
+        };
+        let set_position = |position: &Vec2| {
+            set("x", JsValue::from_f64(position.x() as f64));
+            set("y", JsValue::from_f64(position.y() as f64));
+        };
+        set("type", JsValue::from_str(self.name()));
+        match self {
+            GameEvent::Hyperspace {
+                from,
+                to,
+                destroyed,
+            } => {
+                set("fromX", JsValue::from_f64(from.x() as f64));
+                set("fromY", JsValue::from_f64(from.y() as f64));
+                set("toX", JsValue::from_f64(to.x() as f64));
+                set("toY", JsValue::from_f64(to.y() as f64));
+                set("destroyed", JsValue::from_bool(*destroyed));
+            }
+            GameEvent::WaveStarted { wave } => {
+                set("wave", JsValue::from_f64(*wave as f64));
+            }
+            GameEvent::StateChanged { from, to } => {
+                set("from", JsValue::from_str(from.name()));
+                set("to", JsValue::from_str(to.name()));
+            }
+            GameEvent::ShotFired { position, hostile } => {
+                set_position(position);
+                set("hostile", JsValue::from_bool(*hostile));
+            }
+            GameEvent::AsteroidDestroyed { size, position } => {
+                set_position(position);
+                set("size", JsValue::from_str(size.name()));
+            }
+            GameEvent::SaucerDestroyed { size, position } => {
+                set_position(position);
+                set("size", JsValue::from_str(size.name()));
+            }
+            GameEvent::ShipDestroyed { position, lives } => {
+                set_position(position);
+                set("lives", JsValue::from_f64(*lives as f64));
+            }
+            GameEvent::ScoreChanged { score, gained } => {
+                set("score", JsValue::from_f64(*score as f64));
+                set("gained", JsValue::from_f64(*gained as f64));
+            }
+            GameEvent::LifeGained { lives } => {
+                set("lives", JsValue::from_f64(*lives as f64));
+            }
+            GameEvent::GameOver { score } => {
+                set("score", JsValue::from_f64(*score as f64));
+            }
+        }
+        obj.into()
+    }
+}
+
+/// Events waiting to be picked up, in the order they were raised
+#[derive(Debug, Clone, Default)]
+pub struct EventQueue {
+    events: Vec<GameEvent>,
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Take every queued event, leaving the queue empty
+    pub fn drain(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl Extend<GameEvent> for EventQueue {
+    fn extend<I: IntoIterator<Item = GameEvent>>(&mut self, events: I) {
+        self.events.extend(events);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drains_in_order_and_empties() {
+        let mut queue = EventQueue::new();
+        queue.push(GameEvent::WaveStarted { wave: 1 });
+        queue.extend(vec![
+            GameEvent::LifeGained { lives: 4 },
+            GameEvent::GameOver { score: 10 },
+        ]);
+        assert_eq!(queue.len(), 3);
+        assert_eq!(
+            queue.drain(),
+            vec![
+                GameEvent::WaveStarted { wave: 1 },
+                GameEvent::LifeGained { lives: 4 },
+                GameEvent::GameOver { score: 10 },
+            ]
+        );
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+use core::f32::consts::PI;
+
+use bevy_math::Vec2;
+
+use crate::programs::asteroid::GameObject;
+
+/// Floats per instance: position, heading in radians, brightness and scale
+pub const INSTANCE_SIZE: usize = 6;
+
+/// Placement of every copy of one mesh, in the layout the instanced shader
+/// reads per instance
+#[derive(Debug, Clone, Default)]
+pub struct InstanceBatch {
+    instances: Vec<f32>,
+}
+
+impl InstanceBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.instances.clear();
+    }
+
+    /// Instances batched so far
+    pub fn len(&self) -> usize {
+        self.instances.len() / INSTANCE_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    pub fn instances(&self) -> &[f32] {
+        &self.instances
+    }
+
+    /// Add a copy of `obj` drawn at `position`, usually its interpolated one
+    pub fn push(&mut self, obj: &GameObject, position: Vec2, alpha: f32, brightness: f32) {
+        let angle = obj.interpolated_angle(alpha) * PI / 180.;
+        self.instances.extend_from_slice(&[
+            position.x(),
+            position.y(),
+            angle,
+            brightness,
+            obj.scale.x(),
+            obj.scale.y(),
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instances_carry_placement_in_radians() {
+        let mut obj = GameObject::new();
+        obj.angle = 90.;
+        obj.previous_angle = 90.;
+        obj.scale = bevy_math::Vec3::new(0.5, 0.25, 1.);
+        let mut batch = InstanceBatch::new();
+        batch.push(&obj, Vec2::new(1., 2.), 1., 0.8);
+        batch.push(&obj, Vec2::new(-1., 2.), 1., 1.);
+        assert_eq!(batch.len(), 2);
+
+        let first = &batch.instances()[..INSTANCE_SIZE];
+        assert_eq!(&first[..2], &[1., 2.]);
+        assert!((first[2] - PI / 2.).abs() < 1e-6);
+        assert_eq!(&first[3..], &[0.8, 0.5, 0.25]);
+    }
+}
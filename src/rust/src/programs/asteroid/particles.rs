@@ -0,0 +1,335 @@
+use bevy_math::Vec2;
+use rand::prelude::*;
+use rand_pcg::Pcg32;
+
+use crate::programs::asteroid::events::GameEvent;
+use crate::programs::asteroid::heading;
+use crate::programs::asteroid::ship::{AsteroidSize, SaucerSize};
+use crate::programs::asteroid::simulation::Simulation;
+use crate::programs::asteroid::world::WorldBounds;
+
+/// Particles alive at once, new ones are dropped while the pool is full
+pub const CAPACITY: usize = 1024;
+
+pub use crate::programs::asteroid::batch::VERTEX_SIZE;
+
+/// How a value develops over the lifetime of a particle, from 1 at birth
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Curve {
+    Constant,
+    /// Straight down to 0
+    Linear,
+    /// Holds on longer, then drops to 0
+    Quadratic,
+}
+
+impl Curve {
+    /// Value at `progress`, from 0 at birth to 1 at death
+    pub fn at(self, progress: f32) -> f32 {
+        let t = progress.clamp(0., 1.);
+        match self {
+            Curve::Constant => 1.,
+            Curve::Linear => 1. - t,
+            Curve::Quadratic => 1. - t * t,
+        }
+    }
+}
+
+/// A short line segment flying off, purely cosmetic
+#[derive(Debug, Clone, Copy)]
+pub struct Particle {
+    pub position: Vec2,
+    pub previous_position: Vec2,
+    pub velocity: Vec2,
+    /// Degrees clockwise from up, the segment lies along it
+    pub angle: f32,
+    /// Degrees per millisecond
+    pub spin: f32,
+    pub length: f32,
+    pub age: f32,
+    pub lifetime: f32,
+    pub fade: Curve,
+    pub scale: Curve,
+}
+
+impl Particle {
+    pub fn progress(&self) -> f32 {
+        self.age / self.lifetime
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.age < self.lifetime
+    }
+}
+
+/// Describes a burst of particles
+#[derive(Debug, Clone, Copy)]
+pub struct Emitter {
+    pub count: usize,
+    /// Degrees, the middle of the cone the particles leave in
+    pub direction: f32,
+    /// Degrees to either side of `direction`, 180 sends them everywhere
+    pub spread: f32,
+    pub speed: (f32, f32),
+    /// Milliseconds
+    pub lifetime: (f32, f32),
+    pub length: f32,
+    /// Degrees per millisecond the segments turn at most
+    pub spin: f32,
+    pub fade: Curve,
+    pub scale: Curve,
+}
+
+impl Emitter {
+    /// Quick flashes of something blowing up
+    pub const SPARKS: Emitter = Emitter {
+        count: 12,
+        direction: 0.,
+        spread: 180.,
+        speed: (0.002, 0.006),
+        lifetime: (300., 700.),
+        length: 0.05,
+        spin: 0.,
+        fade: Curve::Linear,
+        scale: Curve::Constant,
+    };
+
+    /// The hull of the ship coming apart, slow and tumbling
+    pub const DEBRIS: Emitter = Emitter {
+        count: 6,
+        direction: 0.,
+        spread: 180.,
+        speed: (0.0005, 0.0015),
+        lifetime: (1500., 2500.),
+        length: 0.25,
+        spin: 0.3,
+        fade: Curve::Quadratic,
+        scale: Curve::Constant,
+    };
+
+    /// Emitted every step while the engine is on, aim it backwards
+    pub const EXHAUST: Emitter = Emitter {
+        count: 2,
+        direction: 0.,
+        spread: 15.,
+        speed: (0.002, 0.004),
+        lifetime: (100., 250.),
+        length: 0.08,
+        spin: 0.,
+        fade: Curve::Linear,
+        scale: Curve::Linear,
+    };
+
+    pub fn aimed(self, direction: f32) -> Self {
+        Self { direction, ..self }
+    }
+
+    pub fn with_count(self, count: usize) -> Self {
+        Self { count, ..self }
+    }
+}
+
+/// Fixed pool of particles. Dead particles are swapped out so the living
+/// ones stay packed, ready to be copied into a single vertex buffer.
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    // Looks only, kept apart from the simulation's random source
+    rng: Pcg32,
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        Self {
+            particles: Vec::with_capacity(CAPACITY),
+            rng: Pcg32::seed_from_u64(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// Burst at `position`, `velocity` is added to every particle
+    pub fn emit(&mut self, emitter: &Emitter, position: Vec2, velocity: Vec2) {
+        let room = CAPACITY - self.particles.len();
+        for _ in 0..emitter.count.min(room) {
+            let direction = emitter.direction + spread(&mut self.rng, emitter.spread);
+            let speed = between(&mut self.rng, emitter.speed);
+            self.particles.push(Particle {
+                position,
+                previous_position: position,
+                velocity: velocity + heading(direction) * speed,
+                angle: self.rng.gen_range(0., 360.),
+                spin: spread(&mut self.rng, emitter.spin),
+                length: emitter.length,
+                age: 0.,
+                lifetime: between(&mut self.rng, emitter.lifetime),
+                fade: emitter.fade,
+                scale: emitter.scale,
+            });
+        }
+    }
+
+    /// Set off the effects of what happened in the game
+    pub fn observe(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::AsteroidDestroyed { size, position } => {
+                let count = match size {
+                    AsteroidSize::Large => 16,
+                    AsteroidSize::Medium => 10,
+                    AsteroidSize::Small => 6,
+                };
+                self.emit(&Emitter::SPARKS.with_count(count), *position, Vec2::zero());
+            }
+            GameEvent::SaucerDestroyed { size, position } => {
+                let count = match size {
+                    SaucerSize::Large => 20,
+                    SaucerSize::Small => 14,
+                };
+                self.emit(&Emitter::SPARKS.with_count(count), *position, Vec2::zero());
+            }
+            GameEvent::ShipDestroyed { position, .. } => {
+                self.emit(&Emitter::SPARKS, *position, Vec2::zero());
+                self.emit(&Emitter::DEBRIS, *position, Vec2::zero());
+            }
+            _ => {}
+        }
+    }
+
+    /// Exhaust behind the player's ship while it thrusts forwards
+    pub fn follow(&mut self, game: &Simulation) {
+        if let Some(ship) = game.ships.get(game.player) {
+            if game.lives > 0 && ship.obj.acceleration.dot(ship.obj.direction) > 0. {
+                let nozzle = ship.obj.position - ship.obj.direction * (1. / 6.);
+                self.emit(
+                    &Emitter::EXHAUST.aimed(ship.obj.angle + 180.),
+                    nozzle,
+                    ship.obj.velocity,
+                );
+            }
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32, bounds: &WorldBounds) {
+        let mut i = 0;
+        while i < self.particles.len() {
+            let particle = &mut self.particles[i];
+            particle.age += delta_time;
+            if !particle.is_alive() {
+                self.particles.swap_remove(i);
+                continue;
+            }
+            particle.previous_position = particle.position;
+            particle.position += particle.velocity * delta_time;
+            particle.angle += particle.spin * delta_time;
+            let wrapped = bounds.wrap_position(particle.position);
+            if wrapped != particle.position {
+                particle.position = wrapped;
+                particle.previous_position = wrapped;
+            }
+            i += 1;
+        }
+    }
+
+    /// Append a line list in world space to `out`, `VERTEX_SIZE` components
+    /// per vertex with the faded brightness last
+    pub fn vertices(&self, alpha: f32, out: &mut Vec<f32>) {
+        for particle in &self.particles {
+            let progress = particle.progress();
+            let centre = particle.previous_position.lerp(particle.position, alpha);
+            let half =
+                heading(particle.angle) * (particle.length * particle.scale.at(progress) / 2.);
+            let brightness = particle.fade.at(progress);
+            for end in [centre - half, centre + half].iter() {
+                out.extend_from_slice(&[end.x(), end.y(), 0., brightness]);
+            }
+        }
+    }
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn between<R: Rng>(rng: &mut R, range: (f32, f32)) -> f32 {
+    if range.1 > range.0 {
+        rng.gen_range(range.0, range.1)
+    } else {
+        range.0
+    }
+}
+
+fn spread<R: Rng>(rng: &mut R, amount: f32) -> f32 {
+    between(rng, (-amount, amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn particles_fade_and_die() {
+        let bounds = WorldBounds::default();
+        let mut system = ParticleSystem::new();
+        system.emit(&Emitter::SPARKS, Vec2::zero(), Vec2::zero());
+        assert_eq!(system.len(), Emitter::SPARKS.count);
+
+        let mut vertices = vec![];
+        system.vertices(1., &mut vertices);
+        assert_eq!(vertices.len(), system.len() * 2 * VERTEX_SIZE);
+        assert!(vertices.chunks(VERTEX_SIZE).all(|vertex| vertex[3] == 1.));
+
+        system.update(250., &bounds);
+        vertices.clear();
+        system.vertices(1., &mut vertices);
+        assert!(vertices.chunks(VERTEX_SIZE).all(|vertex| vertex[3] < 1.));
+
+        system.update(500., &bounds);
+        assert!(system.is_empty());
+    }
+
+    #[test]
+    fn pool_never_grows_past_capacity() {
+        let mut system = ParticleSystem::new();
+        for _ in 0..CAPACITY {
+            system.emit(&Emitter::DEBRIS, Vec2::zero(), Vec2::zero());
+        }
+        assert_eq!(system.len(), CAPACITY);
+        assert_eq!(system.particles.capacity(), CAPACITY);
+    }
+
+    #[test]
+    fn thrusting_ship_leaves_exhaust() {
+        let mut game = Simulation::new(0);
+        let mut system = ParticleSystem::new();
+        system.follow(&game);
+        assert!(system.is_empty());
+
+        let ship = game.ships.get_mut(game.player).unwrap();
+        ship.obj.acceleration = ship.obj.direction * 0.001;
+        system.follow(&game);
+        assert_eq!(system.len(), Emitter::EXHAUST.count);
+        // Leaving backwards, the ship points up
+        assert!(system.particles.iter().all(|p| p.velocity.y() < 0.));
+    }
+
+    #[test]
+    fn curves_start_full_and_end_empty() {
+        for &curve in &[Curve::Linear, Curve::Quadratic] {
+            assert_eq!(curve.at(0.), 1.);
+            assert_eq!(curve.at(1.), 0.);
+        }
+        assert!(Curve::Quadratic.at(0.5) > Curve::Linear.at(0.5));
+        assert_eq!(Curve::Constant.at(0.7), 1.);
+    }
+}
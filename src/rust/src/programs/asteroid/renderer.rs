@@ -0,0 +1,524 @@
+use bevy_math::Vec2;
+use core::f32::consts::PI;
+use std::collections::HashMap;
+
+use crate::canvas::CanvasData;
+use crate::programs::asteroid::backend::{BufferId, BufferUsage, Program, RenderBackend, Uniform};
+use crate::programs::asteroid::batch::{self, LineBatch};
+use crate::programs::asteroid::entity::Handle;
+use crate::programs::asteroid::instancing::{InstanceBatch, INSTANCE_SIZE};
+use crate::programs::asteroid::particles::{self, ParticleSystem};
+use crate::programs::asteroid::ship::{Bullet, Saucer, SpaceShip};
+use crate::programs::asteroid::simulation::Simulation;
+use crate::programs::asteroid::{transform, GameObject, Z_OFFSET};
+
+/// Meshes every entity of a kind shares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Ship,
+    Bullet,
+    /// Both sizes, the model matrix scales it
+    Saucer,
+}
+
+impl Shape {
+    pub const ALL: [Shape; 3] = [Shape::Ship, Shape::Bullet, Shape::Saucer];
+
+    pub fn vertices(self) -> Vec<f32> {
+        match self {
+            Shape::Ship => SpaceShip::vertices(),
+            Shape::Bullet => Bullet::vertices(),
+            Shape::Saucer => Saucer::vertices(),
+        }
+    }
+}
+
+/// Line vertices the streaming buffers start out with room for: a full
+/// particle pool, the scene itself is small next to it
+pub const LINE_CAPACITY: usize = particles::CAPACITY * 2 * 2;
+
+/// Instances the instance stream starts out with room for
+const INSTANCE_CAPACITY: usize = 256;
+
+/// Draws the state of a `Simulation`. Shared shapes are instanced where the
+/// backend allows it; everything else, or everything when it does not, is
+/// collected in one line batch and drawn with a single call.
+pub struct Renderer {
+    instanced: bool,
+    /// Indexed by `Shape`, used when batching them
+    shapes: Vec<Vec<f32>>,
+    /// Indexed by `Shape`, used when instancing them
+    meshes: Vec<BufferId>,
+    /// Outlines are unique, each is built the first time it is seen
+    asteroids: HashMap<Handle, Vec<f32>>,
+    batch: LineBatch,
+    line_stream: BufferId,
+    /// Indexed by `Shape`, used when instancing them
+    instances: Vec<InstanceBatch>,
+    /// Instances of every shape of a frame, one after the other
+    instance_data: Vec<f32>,
+    instance_stream: BufferId,
+}
+
+impl Renderer {
+    pub fn new(backend: &mut dyn RenderBackend) -> Self {
+        let shapes: Vec<Vec<f32>> = Shape::ALL.iter().map(|shape| shape.vertices()).collect();
+        let instanced = backend.supports_instancing();
+        let mut meshes = vec![];
+        if instanced {
+            for vertices in &shapes {
+                let mesh = backend.create_buffer(BufferUsage::Static, vertices.len());
+                backend.write_buffer(mesh, vertices);
+                meshes.push(mesh);
+            }
+        }
+        Self {
+            instanced,
+            shapes,
+            meshes,
+            asteroids: HashMap::new(),
+            batch: LineBatch::with_capacity(LINE_CAPACITY),
+            line_stream: backend
+                .create_buffer(BufferUsage::Stream, LINE_CAPACITY * batch::VERTEX_SIZE),
+            instances: Shape::ALL.iter().map(|_| InstanceBatch::new()).collect(),
+            instance_data: vec![],
+            instance_stream: backend
+                .create_buffer(BufferUsage::Stream, INSTANCE_CAPACITY * INSTANCE_SIZE),
+        }
+    }
+
+    /// Whether shared shapes are drawn through the instanced path
+    pub fn is_instanced(&self) -> bool {
+        self.instanced
+    }
+
+    /// Forget per-entity meshes, entity ids are reused by a new session
+    pub fn reset(&mut self) {
+        self.asteroids.clear();
+    }
+
+    /// `backend` must be the one the renderer was made with
+    pub fn draw(
+        &mut self,
+        backend: &mut dyn RenderBackend,
+        canvas: &CanvasData,
+        game: &Simulation,
+        particles: &ParticleSystem,
+        alpha: f32,
+    ) {
+        backend.clear();
+
+        self.batch.clear();
+        for instances in self.instances.iter_mut() {
+            instances.clear();
+        }
+        if self.instanced {
+            let instances = &mut self.instances;
+            shared_images(game, alpha, |shape, obj, image| {
+                instances[shape as usize].push(obj, image, alpha, 1.)
+            });
+        } else {
+            let (batch, shapes) = (&mut self.batch, &self.shapes);
+            shared_images(game, alpha, |shape, obj, image| {
+                let transform = model_matrix(obj, image, alpha);
+                batch.push_mesh(&shapes[shape as usize], &transform, 1.)
+            });
+        }
+        batch_asteroids(&mut self.batch, &mut self.asteroids, game, alpha);
+        particles.vertices(alpha, self.batch.raw());
+
+        backend.upload_uniform(Uniform::Projection, &projection_matrix(canvas));
+        backend.upload_uniform(Uniform::View, &view_matrix());
+
+        self.instance_data.clear();
+        for batch in &self.instances {
+            self.instance_data.extend_from_slice(batch.instances());
+        }
+        if !self.instance_data.is_empty() {
+            backend.write_buffer(self.instance_stream, &self.instance_data);
+            backend.use_program(Program::Instanced);
+            let mut first = 0;
+            for (shape, batch) in Shape::ALL.iter().zip(&self.instances) {
+                if batch.is_empty() {
+                    continue;
+                }
+                let vertices = self.shapes[*shape as usize].len() / 3;
+                let mesh = self.meshes[*shape as usize];
+                backend.draw_instanced(mesh, vertices, self.instance_stream, first, batch.len());
+                first += batch.len();
+            }
+        }
+
+        if !self.batch.is_empty() {
+            backend.write_buffer(self.line_stream, self.batch.vertices());
+            backend.use_program(Program::Lines);
+            backend.draw_lines(self.line_stream, 0, self.batch.len());
+        }
+    }
+}
+
+/// Every place an entity with a shared shape shows up at: the entity itself,
+/// plus a copy on the far side of every edge it sticks out of, so that it
+/// slides across the edge rather than popping over
+fn shared_images(game: &Simulation, alpha: f32, mut visit: impl FnMut(Shape, &GameObject, Vec2)) {
+    let bounds = &game.bounds;
+    let mut wrapped = |shape: Shape, obj: &GameObject, radius: f32| {
+        let position = obj.interpolated_position(alpha);
+        for image in bounds.images(position, radius) {
+            visit(shape, obj, image);
+        }
+    };
+
+    let blink_interval = game.config.respawn.blink_interval;
+    for ship in game.ships.values() {
+        if ship.is_visible(blink_interval) {
+            wrapped(Shape::Ship, &ship.obj, ship.bounding_radius());
+        }
+    }
+    for bullet in game.bullets.values() {
+        wrapped(Shape::Bullet, &bullet.obj, 0.5);
+    }
+    for saucer in game.saucers.values() {
+        wrapped(Shape::Saucer, &saucer.obj, saucer.bounding_radius());
+    }
+}
+
+/// Add every asteroid to `batch`, wrapped like `shared_images`. Meshes of
+/// asteroids that are gone are dropped from `cache`.
+fn batch_asteroids(
+    batch: &mut LineBatch,
+    cache: &mut HashMap<Handle, Vec<f32>>,
+    game: &Simulation,
+    alpha: f32,
+) {
+    cache.retain(|handle, _| game.asteroids.contains(*handle));
+    for (handle, asteroid) in game.asteroids.iter() {
+        let mesh = cache.entry(handle).or_insert_with(|| asteroid.vertices());
+        let position = asteroid.obj.interpolated_position(alpha);
+        for image in game.bounds.images(position, asteroid.bounding_radius()) {
+            batch.push_mesh(mesh, &model_matrix(&asteroid.obj, image, alpha), 1.);
+        }
+    }
+}
+
+pub fn projection_matrix(canvas: &CanvasData) -> bevy_math::Mat4 {
+    let z_near: f32 = 0.1;
+    let z_far: f32 = 100.0;
+
+    /*  -------- Construct projection matrix -------- */
+    let f = 1. / (canvas.get_fov() / 2.).tan();
+    let range_inv = 1. / (z_near - z_far);
+    bevy_math::mat4(
+        bevy_math::vec4(f / canvas.get_aspect(), 0., 0., 0.),
+        bevy_math::vec4(0., f, 0., 0.),
+        bevy_math::vec4(0., 0., (z_near + z_far) * range_inv, -1.),
+        bevy_math::vec4(0., 0., z_near * z_far * range_inv * 2., 0.),
+    )
+}
+
+/// Moves the world away from the camera
+pub fn view_matrix() -> bevy_math::Mat4 {
+    bevy_math::Mat4::from_translation(bevy_math::Vec3::new(0., 0., Z_OFFSET))
+}
+
+/// Place the object at `position`, usually its interpolated position
+pub fn model_matrix(obj: &GameObject, position: bevy_math::Vec2, alpha: f32) -> bevy_math::Mat4 {
+    let theta_rad = obj.interpolated_angle(alpha) * PI / 180.;
+    let rot = bevy_math::Quat::from_axis_angle(bevy_math::Vec3::new(0., 0., -1.), theta_rad);
+
+    let mut transformation = transform::Transform::identity();
+    transformation.set_translation(bevy_math::Vec3::new(position.x(), position.y(), 0.));
+    transformation.set_rotation(rot);
+    transformation.set_non_uniform_scale(obj.scale);
+    *transformation.value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_math::Mat4;
+
+    /// A call made on a `RecordingBackend`
+    #[derive(Debug, Clone, PartialEq)]
+    enum Call {
+        CreateBuffer {
+            buffer: BufferId,
+            usage: BufferUsage,
+            capacity: usize,
+        },
+        WriteBuffer {
+            buffer: BufferId,
+            len: usize,
+        },
+        UseProgram(Program),
+        UploadUniform(Uniform, Mat4),
+        Clear,
+        DrawLines {
+            buffer: BufferId,
+            first: usize,
+            count: usize,
+        },
+        DrawInstanced {
+            mesh: BufferId,
+            vertices: usize,
+            instances: BufferId,
+            first: usize,
+            count: usize,
+        },
+    }
+
+    /// Keeps every call and the contents of every buffer instead of drawing,
+    /// so frames can be checked without a browser
+    #[derive(Debug, Clone, Default)]
+    struct RecordingBackend {
+        instancing: bool,
+        calls: Vec<Call>,
+        buffers: Vec<Vec<f32>>,
+    }
+
+    impl RecordingBackend {
+        fn new(instancing: bool) -> Self {
+            Self {
+                instancing,
+                ..Self::default()
+            }
+        }
+
+        /// Calls since the last call, e.g. those of a single frame
+        fn take_calls(&mut self) -> Vec<Call> {
+            std::mem::take(&mut self.calls)
+        }
+
+        /// What was last written to `buffer`
+        fn buffer(&self, buffer: BufferId) -> &[f32] {
+            &self.buffers[buffer.0]
+        }
+    }
+
+    impl RenderBackend for RecordingBackend {
+        fn supports_instancing(&self) -> bool {
+            self.instancing
+        }
+
+        fn create_buffer(&mut self, usage: BufferUsage, capacity: usize) -> BufferId {
+            let buffer = BufferId(self.buffers.len());
+            self.buffers.push(vec![]);
+            self.calls.push(Call::CreateBuffer {
+                buffer,
+                usage,
+                capacity,
+            });
+            buffer
+        }
+
+        fn write_buffer(&mut self, buffer: BufferId, data: &[f32]) {
+            self.buffers[buffer.0] = data.to_vec();
+            self.calls.push(Call::WriteBuffer {
+                buffer,
+                len: data.len(),
+            });
+        }
+
+        fn use_program(&mut self, program: Program) {
+            assert!(
+                program != Program::Instanced || self.instancing,
+                "instanced program used without instancing support"
+            );
+            self.calls.push(Call::UseProgram(program));
+        }
+
+        fn upload_uniform(&mut self, uniform: Uniform, value: &Mat4) {
+            self.calls.push(Call::UploadUniform(uniform, *value));
+        }
+
+        fn clear(&mut self) {
+            self.calls.push(Call::Clear);
+        }
+
+        fn draw_lines(&mut self, buffer: BufferId, first: usize, count: usize) {
+            self.calls.push(Call::DrawLines {
+                buffer,
+                first,
+                count,
+            });
+        }
+
+        fn draw_instanced(
+            &mut self,
+            mesh: BufferId,
+            vertices: usize,
+            instances: BufferId,
+            first: usize,
+            count: usize,
+        ) {
+            self.calls.push(Call::DrawInstanced {
+                mesh,
+                vertices,
+                instances,
+                first,
+                count,
+            });
+        }
+    }
+
+    /// A session with its first wave in
+    fn started() -> Simulation {
+        let mut game = Simulation::new(0);
+        game.update(&crate::input::UserInput::new(), 0.);
+        assert!(!game.asteroids.is_empty());
+        game
+    }
+
+    fn canvas() -> CanvasData {
+        CanvasData::new(800., 600., 45., String::from("canvas"))
+    }
+
+    /// Calls made drawing one frame of `game`
+    fn frame(
+        renderer: &mut Renderer,
+        backend: &mut RecordingBackend,
+        game: &Simulation,
+    ) -> Vec<Call> {
+        backend.take_calls();
+        renderer.draw(backend, &canvas(), game, &ParticleSystem::new(), 1.);
+        backend.take_calls()
+    }
+
+    fn count_images(game: &Simulation) -> [usize; 3] {
+        let mut counts = [0; 3];
+        shared_images(game, 1., |shape, _, _| counts[shape as usize] += 1);
+        counts
+    }
+
+    #[test]
+    fn shared_shapes_are_visited_on_both_sides_of_an_edge() {
+        let mut game = Simulation::new(0);
+        assert_eq!(count_images(&game), [1, 0, 0]);
+
+        let player = game.ships.get_mut(game.player).unwrap();
+        player.obj.teleport(Vec2::new(game.bounds.half_width, 0.));
+        assert_eq!(count_images(&game), [2, 0, 0]);
+    }
+
+    #[test]
+    fn asteroid_meshes_follow_the_asteroids() {
+        let mut game = started();
+        let mut batch = LineBatch::new();
+        let mut cache = HashMap::new();
+        batch_asteroids(&mut batch, &mut cache, &game, 1.);
+        assert!(!cache.is_empty());
+        assert!(!batch.is_empty());
+        assert_eq!(cache.len(), game.asteroids.len());
+        assert!(batch.len() >= cache.values().map(|mesh| mesh.len() / 3).sum());
+
+        // Only the meshes of the asteroids still around are kept and drawn
+        let before = batch.len();
+        let gone = game.asteroids.handles()[0];
+        game.asteroids.despawn(gone);
+        batch.clear();
+        batch_asteroids(&mut batch, &mut cache, &game, 1.);
+        assert_eq!(cache.len(), game.asteroids.len());
+        assert!(!cache.contains_key(&gone));
+        assert!(batch.len() < before);
+
+        for handle in game.asteroids.handles() {
+            game.asteroids.despawn(handle);
+        }
+        batch.clear();
+        batch_asteroids(&mut batch, &mut cache, &game, 1.);
+        assert!(cache.is_empty());
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn batched_frame_is_a_single_draw_call() {
+        let mut game = started();
+        let mut firing = crate::input::UserInput::new();
+        firing.spacebar = true;
+        let mut backend = RecordingBackend::new(false);
+        let mut renderer = Renderer::new(&mut backend);
+        assert!(!renderer.is_instanced());
+
+        for _ in 0..2 {
+            // Ship, bullets and asteroids all go through the one batch
+            let player = game.player;
+            game.ships.get_mut(player).unwrap().last_shot = f32::INFINITY;
+            game.update(&firing, 16.);
+            assert!(game.ships.contains(game.player));
+            assert!(!game.bullets.is_empty());
+            assert!(!game.asteroids.is_empty());
+
+            let calls = frame(&mut renderer, &mut backend, &game);
+            let count =
+                |matches: &dyn Fn(&Call) -> bool| calls.iter().filter(|c| matches(c)).count();
+            let line_stream = renderer.line_stream;
+            assert_eq!(count(&|call| *call == Call::Clear), 1);
+            assert_eq!(
+                count(&|call| matches!(call, Call::UploadUniform(Uniform::Projection, _))),
+                1
+            );
+            assert_eq!(count(&|call| matches!(call, Call::WriteBuffer { .. })), 1);
+            assert_eq!(
+                count(
+                    &|call| matches!(call, Call::WriteBuffer { buffer, .. } if *buffer == line_stream)
+                ),
+                1
+            );
+            assert_eq!(count(&|call| matches!(call, Call::DrawLines { .. })), 1);
+            assert_eq!(count(&|call| matches!(call, Call::DrawInstanced { .. })), 0);
+            // Buffers are made once, frames only rewrite them
+            assert_eq!(count(&|call| matches!(call, Call::CreateBuffer { .. })), 0);
+
+            let vertices = renderer.batch.len();
+            assert!(vertices > 0);
+            assert!(calls.contains(&Call::DrawLines {
+                buffer: line_stream,
+                first: 0,
+                count: vertices,
+            }));
+            assert_eq!(
+                backend.buffer(line_stream).len(),
+                vertices * batch::VERTEX_SIZE
+            );
+        }
+    }
+
+    #[test]
+    fn instanced_frame_draws_each_shape_present_once() {
+        let mut game = started();
+        let mut backend = RecordingBackend::new(true);
+        let mut renderer = Renderer::new(&mut backend);
+        assert!(renderer.is_instanced());
+
+        // Only the player's ship is shared, the asteroids are batched
+        let calls = frame(&mut renderer, &mut backend, &game);
+        let instanced: Vec<_> = calls
+            .iter()
+            .filter_map(|call| match call {
+                Call::DrawInstanced { mesh, count, .. } => Some((*mesh, *count)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(instanced, vec![(renderer.meshes[Shape::Ship as usize], 1)]);
+        let lines = calls
+            .iter()
+            .filter(|call| matches!(call, Call::DrawLines { .. }))
+            .count();
+        assert_eq!(lines, 1);
+
+        let vertices: usize = game
+            .asteroids
+            .iter()
+            .map(|(_, a)| a.vertices().len() / 3)
+            .sum();
+        assert!(backend.buffer(renderer.line_stream).len() >= vertices * batch::VERTEX_SIZE);
+
+        for handle in game.asteroids.handles() {
+            game.asteroids.despawn(handle);
+        }
+        let calls = frame(&mut renderer, &mut backend, &game);
+        assert!(!calls
+            .iter()
+            .any(|call| matches!(call, Call::DrawLines { .. })));
+    }
+}
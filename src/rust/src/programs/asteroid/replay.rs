@@ -0,0 +1,324 @@
+use std::convert::TryInto;
+
+use crate::input::UserInput;
+use crate::programs::asteroid::simulation::RULES_VERSION;
+use crate::programs::asteroid::world::WorldBounds;
+
+/// The keys the asteroid simulation reacts to, packed into a single byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InputBits(pub u8);
+
+impl InputBits {
+    pub const W: u8 = 1;
+    pub const A: u8 = 1 << 1;
+    pub const S: u8 = 1 << 2;
+    pub const D: u8 = 1 << 3;
+    pub const SPACE: u8 = 1 << 4;
+    pub const SHIFT: u8 = 1 << 5;
+
+    pub fn from_input(input: &UserInput) -> Self {
+        let mut bits = 0;
+        if input.keyboard_w {
+            bits |= Self::W;
+        }
+        if input.keyboard_a {
+            bits |= Self::A;
+        }
+        if input.keyboard_s {
+            bits |= Self::S;
+        }
+        if input.keyboard_d {
+            bits |= Self::D;
+        }
+        if input.spacebar {
+            bits |= Self::SPACE;
+        }
+        if input.keyboard_shift {
+            bits |= Self::SHIFT;
+        }
+        Self(bits)
+    }
+
+    pub fn to_input(self) -> UserInput {
+        let mut input = UserInput::new();
+        input.keyboard_w = self.0 & Self::W != 0;
+        input.keyboard_a = self.0 & Self::A != 0;
+        input.keyboard_s = self.0 & Self::S != 0;
+        input.keyboard_d = self.0 & Self::D != 0;
+        input.spacebar = self.0 & Self::SPACE != 0;
+        input.keyboard_shift = self.0 & Self::SHIFT != 0;
+        input
+    }
+}
+
+/// Identifies a serialized recording
+const MAGIC: &[u8; 4] = b"AREP";
+/// Version of the byte layout produced by `Recording::encode`
+pub const FORMAT_VERSION: u8 = 2;
+// magic, format version, rules version, seed, world half width and half height
+const HEADER_LEN: usize = 4 + 1 + 2 + 8 + 4 + 4;
+/// A day of play at 60 ticks per second, guards against absurd run lengths
+const MAX_TICKS: usize = 60 * 60 * 60 * 24;
+
+/// Everything needed to play a session again: its seed and the input of
+/// every simulation tick
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    pub seed: u64,
+    /// Size of the world the session was played in
+    pub bounds: WorldBounds,
+    pub ticks: Vec<InputBits>,
+}
+
+impl Recording {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            bounds: WorldBounds::default(),
+            ticks: vec![],
+        }
+    }
+
+    pub fn record(&mut self, input: &UserInput) {
+        self.ticks.push(InputBits::from_input(input));
+    }
+
+    /// Serialize into the versioned replay format.
+    ///
+    /// All numbers are little endian. The header is followed by runs of
+    /// identical ticks, each stored as the input byte and a LEB128 run length.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 16);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&RULES_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.bounds.half_width.to_le_bytes());
+        bytes.extend_from_slice(&self.bounds.half_height.to_le_bytes());
+
+        let mut ticks = self.ticks.iter().peekable();
+        while let Some(tick) = ticks.next() {
+            let mut run: u32 = 1;
+            while ticks.peek() == Some(&tick) && run < u32::MAX {
+                ticks.next();
+                run += 1;
+            }
+            bytes.push(tick.0);
+            write_varint(&mut bytes, run);
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+            return Err(String::from("Data is not an asteroid replay"));
+        }
+        let format_version = bytes[4];
+        if format_version != FORMAT_VERSION {
+            return Err(format!(
+                "Unknown replay format version {}, expected {}",
+                format_version, FORMAT_VERSION
+            ));
+        }
+        let rules_version = u16::from_le_bytes(bytes[5..7].try_into().unwrap());
+        if rules_version != RULES_VERSION {
+            return Err(format!(
+                "Replay was recorded with game rules version {}, this build plays version {}",
+                rules_version, RULES_VERSION
+            ));
+        }
+        let seed = u64::from_le_bytes(bytes[7..15].try_into().unwrap());
+        let bounds = WorldBounds::new(
+            f32::from_le_bytes(bytes[15..19].try_into().unwrap()),
+            f32::from_le_bytes(bytes[19..23].try_into().unwrap()),
+        );
+
+        let mut ticks = vec![];
+        let mut stream = &bytes[HEADER_LEN..];
+        while let Some((&tick, rest)) = stream.split_first() {
+            let (run, rest) = read_varint(rest)?;
+            if ticks.len() + run as usize > MAX_TICKS {
+                return Err(String::from("Replay is longer than any session can be"));
+            }
+            ticks.resize(ticks.len() + run as usize, InputBits(tick));
+            stream = rest;
+        }
+        Ok(Self {
+            seed,
+            bounds,
+            ticks,
+        })
+    }
+}
+
+/// Encoded replay as URL-safe base64 without padding, so runs can be shared as links
+pub fn to_base64(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+pub fn from_base64(data: &str) -> Result<Vec<u8>, String> {
+    base64::decode_config(data.trim(), base64::URL_SAFE_NO_PAD)
+        .map_err(|e| format!("Invalid replay encoding: {}", e))
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8]) -> Result<(u32, &[u8]), String> {
+    let mut value: u32 = 0;
+    for (i, byte) in bytes.iter().enumerate().take(5) {
+        value |= ((byte & 0x7f) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &bytes[i + 1..]));
+        }
+    }
+    Err(String::from("Replay input stream is truncated"))
+}
+
+/// Feeds a recording back into the simulation one tick at a time
+#[derive(Debug, Clone)]
+pub struct Playback {
+    recording: Recording,
+    cursor: usize,
+}
+
+impl Playback {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            cursor: 0,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.recording.seed
+    }
+
+    pub fn bounds(&self) -> WorldBounds {
+        self.recording.bounds
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.recording.ticks.len()
+    }
+
+    /// Input for the next tick, `None` once the recording has run out
+    pub fn next_input(&mut self) -> Option<UserInput> {
+        let tick = self.recording.ticks.get(self.cursor)?;
+        self.cursor += 1;
+        Some(tick.to_input())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::asteroid::simulation::Simulation;
+
+    #[test]
+    fn input_bits_round_trip() {
+        for bits in 0..64 {
+            let input = InputBits(bits).to_input();
+            assert_eq!(InputBits::from_input(&input), InputBits(bits));
+        }
+    }
+
+    fn sample() -> Recording {
+        let mut recording = Recording::new(0xdead_beef_0123);
+        recording.bounds = WorldBounds::new(8., 3.5);
+        recording.ticks = vec![InputBits(0); 300];
+        recording
+            .ticks
+            .extend(vec![InputBits(InputBits::W | InputBits::SPACE); 3]);
+        recording.ticks.push(InputBits(31));
+        recording
+            .ticks
+            .extend(vec![InputBits(InputBits::A); 70_000]);
+        recording
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let recording = sample();
+        let bytes = recording.encode();
+        // Four runs, none longer than three varint bytes
+        assert!(bytes.len() <= HEADER_LEN + 4 * 4);
+        assert_eq!(Recording::decode(&bytes).unwrap(), recording);
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        let recording = sample();
+        let link = to_base64(&recording.encode());
+        assert!(link
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        let bytes = from_base64(&link).unwrap();
+        assert_eq!(Recording::decode(&bytes).unwrap(), recording);
+    }
+
+    #[test]
+    fn empty_recording_round_trip() {
+        let recording = Recording::new(3);
+        assert_eq!(Recording::decode(&recording.encode()).unwrap(), recording);
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let mut bytes = sample().encode();
+        bytes[4] = FORMAT_VERSION + 1;
+        assert!(Recording::decode(&bytes)
+            .unwrap_err()
+            .contains("format version"));
+
+        let mut bytes = sample().encode();
+        bytes[5..7].copy_from_slice(&(RULES_VERSION + 1).to_le_bytes());
+        assert!(Recording::decode(&bytes)
+            .unwrap_err()
+            .contains("rules version"));
+    }
+
+    #[test]
+    fn rejects_malformed_data() {
+        assert!(Recording::decode(b"not a replay at all").is_err());
+        let mut bytes = sample().encode();
+        bytes.push(InputBits::D);
+        bytes.push(0x80);
+        assert!(Recording::decode(&bytes).is_err());
+        assert!(from_base64("***").is_err());
+    }
+
+    #[test]
+    fn playback_reproduces_the_session() {
+        let mut live = Simulation::new(7);
+        live.bounds = WorldBounds::new(6., 4.);
+        let mut recording = Recording::new(7);
+        recording.bounds = live.bounds;
+        let mut input = UserInput::new();
+        for tick in 0..1500 {
+            input.keyboard_w = tick % 200 < 90;
+            input.keyboard_a = tick % 70 < 15;
+            input.spacebar = tick % 25 < 3;
+            recording.record(&input);
+            live.update(&input, 16.);
+        }
+
+        let mut playback = Playback::new(Recording::decode(&recording.encode()).unwrap());
+        let mut replayed = Simulation::new(playback.seed());
+        replayed.bounds = playback.bounds();
+        while let Some(input) = playback.next_input() {
+            replayed.update(&input, 16.);
+        }
+
+        assert!(playback.is_finished());
+        assert_eq!(replayed.score, live.score);
+        assert_eq!(replayed.lives, live.lives);
+        assert_eq!(replayed.ship().obj.position, live.ship().obj.position);
+        assert_eq!(replayed.asteroids.handles(), live.asteroids.handles());
+    }
+}
@@ -1,16 +1,130 @@
+use crate::gl_setup::GlVersion;
+
 pub const F_SHADER: &str = r#"
+    precision mediump float;
+
+    varying float vBrightness;
+
     void main(void) {
-      gl_FragColor = vec4(1.0, 1.0, 1.0, 1.0);
+      gl_FragColor = vec4(vec3(vBrightness), 1.0);
     }
 "#;
 
+// The w component carries the brightness, meshes with 3 components per
+// vertex get the default of 1
 pub const V_SHADER: &str = r#"
+    attribute vec4 aVertexPosition;
+
+    uniform mat4 uMVMatrix;
+    uniform mat4 uPMatrix;
+
+    varying float vBrightness;
+
+    void main(void) {
+      vBrightness = aVertexPosition.w;
+      gl_Position = uPMatrix * uMVMatrix * vec4(aVertexPosition.xyz, 1.0);
+    }
+"#;
+
+// Draws many copies of one mesh, the placement of each copy comes from the
+// per-instance attributes: position, heading in radians, brightness and scale.
+// `uMVMatrix` only moves the scene away from the camera.
+pub const INSTANCED_V_SHADER: &str = r#"
     attribute vec3 aVertexPosition;
+    attribute vec4 aInstance;
+    attribute vec2 aInstanceScale;
 
     uniform mat4 uMVMatrix;
     uniform mat4 uPMatrix;
 
+    varying float vBrightness;
+
+    void main(void) {
+      vec2 scaled = aVertexPosition.xy * aInstanceScale;
+      // Headings turn clockwise
+      float c = cos(aInstance.z);
+      float s = sin(aInstance.z);
+      vec2 turned = vec2(scaled.x * c + scaled.y * s, scaled.y * c - scaled.x * s);
+      vBrightness = aInstance.w;
+      vec4 position = vec4(turned + aInstance.xy, aVertexPosition.z, 1.0);
+      gl_Position = uPMatrix * uMVMatrix * position;
+    }
+"#;
+
+// GLSL ES 3.00 variants for WebGL2. Attribute locations are fixed so vertex
+// array objects can be set up before the programs are used, and the camera
+// comes from a uniform block shared by both programs.
+pub const F_SHADER_300: &str = r#"#version 300 es
+    precision mediump float;
+
+    in float vBrightness;
+
+    out vec4 fragColor;
+
     void main(void) {
-      gl_Position = uPMatrix * uMVMatrix * vec4(aVertexPosition, 1.0);
+      fragColor = vec4(vec3(vBrightness), 1.0);
     }
 "#;
+
+pub const V_SHADER_300: &str = r#"#version 300 es
+    layout(location = 0) in vec4 aVertexPosition;
+
+    layout(std140) uniform Camera {
+      mat4 uPMatrix;
+      mat4 uMVMatrix;
+    };
+
+    out float vBrightness;
+
+    void main(void) {
+      vBrightness = aVertexPosition.w;
+      gl_Position = uPMatrix * uMVMatrix * vec4(aVertexPosition.xyz, 1.0);
+    }
+"#;
+
+pub const INSTANCED_V_SHADER_300: &str = r#"#version 300 es
+    layout(location = 0) in vec3 aVertexPosition;
+    layout(location = 1) in vec4 aInstance;
+    layout(location = 2) in vec2 aInstanceScale;
+
+    layout(std140) uniform Camera {
+      mat4 uPMatrix;
+      mat4 uMVMatrix;
+    };
+
+    out float vBrightness;
+
+    void main(void) {
+      vec2 scaled = aVertexPosition.xy * aInstanceScale;
+      // Headings turn clockwise
+      float c = cos(aInstance.z);
+      float s = sin(aInstance.z);
+      vec2 turned = vec2(scaled.x * c + scaled.y * s, scaled.y * c - scaled.x * s);
+      vBrightness = aInstance.w;
+      vec4 position = vec4(turned + aInstance.xy, aVertexPosition.z, 1.0);
+      gl_Position = uPMatrix * uMVMatrix * position;
+    }
+"#;
+
+/// Vertex and fragment source of the line program for `version`
+pub fn sources(version: GlVersion) -> (&'static str, &'static str) {
+    match version {
+        GlVersion::WebGl1 => (V_SHADER, F_SHADER),
+        GlVersion::WebGl2 => (V_SHADER_300, F_SHADER_300),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webgl2_sources_start_with_the_version() {
+        let (vertex, fragment) = sources(GlVersion::WebGl2);
+        for source in [vertex, fragment, INSTANCED_V_SHADER_300].iter() {
+            // Nothing, not even a blank line, may come before the directive
+            assert!(source.starts_with("#version 300 es\n"));
+        }
+        assert_eq!(sources(GlVersion::WebGl1), (V_SHADER, F_SHADER));
+    }
+}
@@ -1,159 +1,266 @@
 use std::f32::consts::PI;
 
-use crate::programs::asteroid::get_vec2_from_vec3;
-use crate::programs::asteroid::WebGlBuffer;
-use crate::utils::console_log;
+use bevy_math::Vec2;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::programs::asteroid::{Drawable, GameObject};
-use web_sys::WebGlRenderingContext as GL;
-
-use super::get_matrix_rotation;
+use crate::programs::asteroid::collision::{self, Hull};
+use crate::programs::asteroid::config::{BulletConfig, ShipConfig};
+use crate::programs::asteroid::entity::Handle;
+use crate::programs::asteroid::{heading, GameObject};
 
+#[derive(Serialize, Deserialize)]
 pub struct SpaceShip {
     pub obj: GameObject,
     pub last_shot: f32,
+    /// Time since the last hyperspace jump
+    pub last_jump: f32,
+    /// Time left during which nothing can hit the ship
+    pub invulnerable: f32,
 }
 
 impl SpaceShip {
-    pub fn new(gl: &GL, offset_z: f32) -> Self {
-        let gl_buffer = SpaceShip::init_buffers(gl);
-        let buffers = Drawable::new(gl_buffer.0, gl_buffer.1, gl_buffer.2);
-        let g_object = GameObject::new(buffers, offset_z);
+    pub fn new() -> Self {
         Self {
-            obj: g_object,
+            obj: GameObject::new(),
             last_shot: 0.,
+            last_jump: 0.,
+            invulnerable: 0.,
         }
     }
 
-    fn init_buffers(gl: &GL) -> (i32, i32, WebGlBuffer) {
-        let position_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&position_buffer));
+    pub fn from_config(config: &ShipConfig) -> Self {
+        let mut ship = Self::new();
+        ship.obj.max_speed = config.max_speed;
+        ship.obj.drag = config.drag;
+        ship
+    }
 
-        // Construct spaceship
-        let vertices: Vec<(f32, f32, f32)> = vec![
-            (-1., -1., 0.),
-            (0., 1., 0.),
-            (0., 1., 0.),
-            (1., -1., 0.),
-            (1., -1., 0.),
-            (0., -0.5, 0.),
-            (0., -0.5, 0.),
-            (-1., -1., 0.),
-        ];
+    /// Closed outline in object space
+    pub const OUTLINE: [(f32, f32, f32); 4] = [
+        (-1. / 3., -1. / 3., 0.),
+        (0., 1. / 3., 0.),
+        (1. / 3., -1. / 3., 0.),
+        (0., -0.5 / 3., 0.),
+    ];
 
+    /// Line list of the ship outline, three components per vertex
+    pub fn vertices() -> Vec<f32> {
         let mut result_array: Vec<f32> = Vec::new();
-        for elem in vertices.iter() {
-            result_array.push(elem.0 / 3.);
-            result_array.push(elem.1 / 3.);
-            result_array.push(elem.2);
-        }
-        unsafe {
-            let vert_array = js_sys::Float32Array::view(&result_array);
-            gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &vert_array, GL::STATIC_DRAW);
+        for (i, elem) in Self::OUTLINE.iter().enumerate() {
+            let next = Self::OUTLINE[(i + 1) % Self::OUTLINE.len()];
+            result_array.extend_from_slice(&[elem.0, elem.1, elem.2]);
+            result_array.extend_from_slice(&[next.0, next.1, next.2]);
         }
+        result_array
+    }
+
+    pub fn hull(&self) -> Hull<'_> {
+        Hull::new(&self.obj, &Self::OUTLINE)
+    }
+
+    pub fn bounding_radius(&self) -> f32 {
+        self.hull().bounding_radius()
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable > 0.
+    }
 
-        (3, 8, position_buffer)
+    /// Whether the ship shows up this frame, it blinks while invulnerable
+    pub fn is_visible(&self, blink_interval: f32) -> bool {
+        !self.is_invulnerable() || ((self.invulnerable / blink_interval) as u32).is_multiple_of(2)
     }
 
     pub fn update(&mut self, delta_time: f32) {
         self.last_shot += delta_time;
+        self.last_jump += delta_time;
+        self.invulnerable = (self.invulnerable - delta_time).max(0.);
         self.obj.update(delta_time);
+    }
+}
 
-        /* Wrap player */
-        if (self.obj.position.y() / 11.).abs() + 0.6 > 1. {
-            self.obj.position.set_y(-self.obj.position.y());
-        }
-        if (self.obj.position.x() / 11.).abs() * 1.6 > 1. {
-            self.obj.position.set_x(-self.obj.position.x());
-        }
-
-        /* Apply drag */
-        self.obj.speed = self.obj.speed * 0.99;
+impl Default for SpaceShip {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-pub struct Bullet(pub GameObject);
+/// Whoever fired a bullet, each side has its own store of entities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Shooter {
+    Ship(Handle),
+    Saucer(Handle),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Bullet {
+    pub obj: GameObject,
+    pub owner: Option<Shooter>,
+    /// Time since the bullet was fired
+    pub age: f32,
+    pub time_to_live: f32,
+    /// Distance covered since the bullet was fired
+    pub travelled: f32,
+    pub range: f32,
+}
 
 impl Bullet {
-    pub fn new(gl: &GL, offset_z: f32) -> Self {
-        let gl_buffer = Self::init_buffers(gl);
-        let buffers = Drawable::new(gl_buffer.0, gl_buffer.1, gl_buffer.2);
-        let g_object = GameObject::new(buffers, offset_z);
-        Self(g_object)
+    pub fn new() -> Self {
+        Self {
+            obj: GameObject::new(),
+            owner: None,
+            age: 0.,
+            time_to_live: f32::INFINITY,
+            travelled: 0.,
+            range: f32::INFINITY,
+        }
     }
 
-    fn init_buffers(gl: &GL) -> (i32, i32, WebGlBuffer) {
-        let position_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&position_buffer));
+    /// A bullet leaving the nose of `ship`
+    pub fn fired_by(ship: &SpaceShip, owner: Handle, config: &BulletConfig) -> Self {
+        let mut bullet = Self::new();
+        bullet.owner = Some(Shooter::Ship(owner));
+        bullet.time_to_live = config.time_to_live;
+        bullet.range = config.range;
+        bullet.obj.angle = ship.obj.angle;
+        bullet.obj.direction = ship.obj.direction;
+        bullet.obj.position = ship.obj.position;
+        bullet.obj.velocity = ship.obj.direction * config.speed;
+        if config.inherit_velocity {
+            bullet.obj.velocity += ship.obj.velocity;
+        }
+        bullet
+    }
+
+    /// A bullet leaving the centre of `saucer` at `angle` degrees
+    pub fn fired_from(
+        saucer: &Saucer,
+        owner: Handle,
+        angle: f32,
+        speed: f32,
+        config: &BulletConfig,
+    ) -> Self {
+        let mut bullet = Self::new();
+        bullet.owner = Some(Shooter::Saucer(owner));
+        bullet.time_to_live = config.time_to_live;
+        bullet.range = config.range;
+        bullet.obj.angle = angle;
+        bullet.obj.direction = heading(angle);
+        bullet.obj.position = saucer.obj.position;
+        bullet.obj.velocity = bullet.obj.direction * speed;
+        bullet
+    }
 
-        // Construct spaceship
-        let vertices: Vec<(f32, f32, f32)> = vec![(0., 0.5, 0.), (0., 0., 0.)];
+    /// Fired by a saucer, it threatens the player rather than scoring
+    pub fn is_hostile(&self) -> bool {
+        matches!(self.owner, Some(Shooter::Saucer(_)))
+    }
 
+    pub fn is_spent(&self) -> bool {
+        self.age >= self.time_to_live || self.travelled >= self.range
+    }
+
+    /// The trail in object space, the tip leads the bullet's direction
+    pub const TRAIL: [(f32, f32, f32); 2] = [(0., 0.5, 0.), (0., 0., 0.)];
+
+    /// Line list of the bullet trail, three components per vertex
+    pub fn vertices() -> Vec<f32> {
         let mut result_array: Vec<f32> = Vec::new();
-        for elem in vertices.iter() {
+        for elem in Self::TRAIL.iter() {
             result_array.push(elem.0);
             result_array.push(elem.1);
             result_array.push(elem.2);
         }
-        unsafe {
-            let vert_array = js_sys::Float32Array::view(&result_array);
-            gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &vert_array, GL::STATIC_DRAW);
-        }
+        result_array
+    }
 
-        (3, 2, position_buffer)
+    /// End points of the trail as currently placed in the world
+    pub fn world_trail(&self) -> (Vec2, Vec2) {
+        let trail = collision::to_world(&self.obj, Self::TRAIL.iter());
+        (trail[0], trail[1])
     }
 
     pub fn update(&mut self, delta_time: f32) {
-        self.0.update(delta_time);
+        self.age += delta_time;
+        self.travelled += self.obj.velocity.length() * delta_time;
+        self.obj.update(delta_time);
     }
 }
 
-pub struct Asteroid {
-    pub obj: GameObject,
+impl Default for Bullet {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl PartialEq for Asteroid {
-    fn eq(&self, other: &Self) -> bool {
-        // Comparing memory addresses
-        self == other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AsteroidSize {
+    Large,
+    Medium,
+    Small,
+}
+
+impl AsteroidSize {
+    pub fn name(self) -> &'static str {
+        match self {
+            AsteroidSize::Large => "large",
+            AsteroidSize::Medium => "medium",
+            AsteroidSize::Small => "small",
+        }
     }
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct Asteroid {
+    pub obj: GameObject,
+    /// Jittered outline in object space, already scaled by the radius
+    pub outline: Vec<(f32, f32, f32)>,
+}
 
 // RADIUSES = [4, 2.8, 1.5];
 impl Asteroid {
-
-    pub fn new(gl: &GL, offset_z: f32, radius: f32) -> Self {
-        let gl_buffer = Self::init_buffers(gl, radius);
-        let buffers = Drawable::new(gl_buffer.0, gl_buffer.1, gl_buffer.2);
-        let mut g_object = GameObject::new(buffers, offset_z);
+    pub fn new<R: Rng>(radius: f32, rng: &mut R) -> Self {
+        let mut g_object = GameObject::new();
         g_object.radius = radius;
-        Self { obj: g_object }
+        Self {
+            obj: g_object,
+            outline: Self::generate_outline(radius, rng),
+        }
     }
 
-    fn init_buffers(gl: &GL, radius: f32) -> (i32, i32, WebGlBuffer) {
-        let position_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&position_buffer));
-
+    fn generate_outline<R: Rng>(radius: f32, rng: &mut R) -> Vec<(f32, f32, f32)> {
         // Construct asteroid
-        let mut rng = rand::thread_rng();
         let mut points: Vec<(f32, f32, f32)> = vec![];
         for i in 0..12 {
-            let rotation = (i as f32 / 12.) as f32 * 2. * PI;
+            let rotation = (i as f32 / 12.) * 2. * PI;
             let x = rotation.cos() + rng.gen_range(-0.5, 0.5);
             let y = rotation.sin() + rng.gen_range(-0.5, 0.5);
             let vert_dist = radius; // + rng.gen_range(0.3 * radius, 0.6 * radius);
-            // points.push((x * vert_dist, y * vert_dist, 0.));
             points.push((x * vert_dist, y * vert_dist, 0.));
         }
+        points
+    }
+
+    /// Size tier, small asteroids no longer split when hit
+    pub fn size(&self) -> AsteroidSize {
+        if self.obj.radius >= 0.75 {
+            AsteroidSize::Large
+        } else if self.obj.radius > 0.3 {
+            AsteroidSize::Medium
+        } else {
+            AsteroidSize::Small
+        }
+    }
 
+    /// Line list of the closed outline, three components per vertex
+    pub fn vertices(&self) -> Vec<f32> {
         let mut result_array: Vec<f32> = Vec::new();
-        let first =points.get(0).unwrap().clone();
+        let first = self.outline[0];
         result_array.push(first.0);
         result_array.push(first.1);
         result_array.push(first.2);
-        for elem in points.iter() {
+        for elem in self.outline.iter() {
             result_array.push(elem.0);
             result_array.push(elem.1);
             result_array.push(elem.2);
@@ -164,15 +271,120 @@ impl Asteroid {
         result_array.push(first.0);
         result_array.push(first.1);
         result_array.push(first.2);
-        unsafe {
-            let vert_array = js_sys::Float32Array::view(&result_array);
-            gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &vert_array, GL::STATIC_DRAW);
+        result_array
+    }
+
+    /// Radius around the position that holds the whole jittered outline
+    pub fn bounding_radius(&self) -> f32 {
+        self.hull().bounding_radius()
+    }
+
+    pub fn hull(&self) -> Hull<'_> {
+        Hull::new(&self.obj, &self.outline)
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.obj.update(delta_time);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SaucerSize {
+    Large,
+    Small,
+}
+
+impl SaucerSize {
+    pub fn name(self) -> &'static str {
+        match self {
+            SaucerSize::Large => "large",
+            SaucerSize::Small => "small",
         }
+    }
 
-        (3, points.len() as i32 * 2 + 2, position_buffer)
+    /// Scale applied to the shared outline
+    pub fn scale(self) -> f32 {
+        match self {
+            SaucerSize::Large => 0.5,
+            SaucerSize::Small => 0.25,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Saucer {
+    pub obj: GameObject,
+    pub size: SaucerSize,
+    /// 1 when crossing the field to the right, -1 to the left
+    pub course: f32,
+    pub last_shot: f32,
+    /// Time left until the next zig-zag turn
+    pub next_turn: f32,
+    /// Distance left to cover before the saucer leaves the field
+    pub distance_left: f32,
+}
+
+impl Saucer {
+    pub fn new(size: SaucerSize) -> Self {
+        let mut obj = GameObject::new();
+        let scale = size.scale();
+        obj.scale = bevy_math::Vec3::new(scale, scale, scale);
+        Self {
+            obj,
+            size,
+            course: 1.,
+            last_shot: 0.,
+            next_turn: 0.,
+            distance_left: f32::INFINITY,
+        }
+    }
+
+    /// Closed outline in object space, the hull with the dome on top
+    pub const OUTLINE: [(f32, f32, f32); 8] = [
+        (-1., 0., 0.),
+        (-0.4, -0.35, 0.),
+        (0.4, -0.35, 0.),
+        (1., 0., 0.),
+        (0.4, 0.3, 0.),
+        (0.25, 0.6, 0.),
+        (-0.25, 0.6, 0.),
+        (-0.4, 0.3, 0.),
+    ];
+
+    /// Line list of the outline plus the rim and the base of the dome,
+    /// three components per vertex
+    pub fn vertices() -> Vec<f32> {
+        let mut result_array: Vec<f32> = Vec::new();
+        for (i, elem) in Self::OUTLINE.iter().enumerate() {
+            let next = Self::OUTLINE[(i + 1) % Self::OUTLINE.len()];
+            result_array.extend_from_slice(&[elem.0, elem.1, elem.2]);
+            result_array.extend_from_slice(&[next.0, next.1, next.2]);
+        }
+        for &(from, to) in &[(0, 3), (4, 7)] {
+            let (from, to) = (Self::OUTLINE[from], Self::OUTLINE[to]);
+            result_array.extend_from_slice(&[from.0, from.1, from.2]);
+            result_array.extend_from_slice(&[to.0, to.1, to.2]);
+        }
+        result_array
+    }
+
+    pub fn hull(&self) -> Hull<'_> {
+        Hull::new(&self.obj, &Self::OUTLINE)
+    }
+
+    pub fn bounding_radius(&self) -> f32 {
+        self.hull().bounding_radius()
+    }
+
+    /// Crossed the whole field once
+    pub fn has_left(&self) -> bool {
+        self.distance_left <= 0.
     }
 
     pub fn update(&mut self, delta_time: f32) {
+        self.last_shot += delta_time;
+        self.next_turn -= delta_time;
+        self.distance_left -= self.obj.velocity.x().abs() * delta_time;
         self.obj.update(delta_time);
     }
 }
@@ -0,0 +1,1177 @@
+use rand::prelude::*;
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use crate::input::UserInput;
+use crate::programs::asteroid::broadphase::SpatialHash;
+use crate::programs::asteroid::config::GameConfig;
+use crate::programs::asteroid::entity::{EntityStore, Handle};
+use crate::programs::asteroid::events::{EventQueue, GameEvent};
+use crate::programs::asteroid::heading;
+use crate::programs::asteroid::ship::{
+    Asteroid, AsteroidSize, Bullet, Saucer, SaucerSize, Shooter, SpaceShip,
+};
+use crate::programs::asteroid::world::WorldBounds;
+
+/// Bump whenever a rule change makes existing recordings play out differently
+pub const RULES_VERSION: u16 = 12;
+
+/// Random source of a game session. Seeded explicitly so that the same seed
+/// and the same inputs always play out the same way.
+pub type GameRng = Pcg32;
+
+/// The asteroid game rules, free of any rendering concerns.
+///
+/// Everything the game needs to advance lives here so that it can be stepped
+/// natively (e.g. in `cargo test`) and drawn by whichever renderer reads it.
+#[derive(Serialize, Deserialize)]
+pub struct Simulation {
+    // Store iteration order feeds into the RNG draws, keep it deterministic
+    pub ships: EntityStore<SpaceShip>,
+    pub player: Handle,
+    pub bullets: EntityStore<Bullet>,
+    pub asteroids: EntityStore<Asteroid>,
+    pub saucers: EntityStore<Saucer>,
+    pub score: u64,
+    /// Multiplier applied to the points of the next hit
+    pub combo: f32,
+    pub lives: u8,
+    /// Number of the current wave, 0 before the first one came in
+    pub wave: u32,
+    /// Set before the first update, everything wraps around these edges
+    pub bounds: WorldBounds,
+    pub config: GameConfig,
+    rng: GameRng,
+    /// Time since the last wave was cleared
+    wave_timer: f32,
+    /// Time since the ship was lost
+    respawn_timer: f32,
+    /// Time spent without a saucer around
+    saucer_timer: f32,
+    // Handed to the frontend after every update
+    #[serde(skip)]
+    events: EventQueue,
+    // Rebuilt every tick, only kept to reuse its allocations
+    #[serde(skip)]
+    broadphase: SpatialHash,
+}
+
+impl Simulation {
+    pub fn new(seed: u64) -> Self {
+        Self::with_config(seed, GameConfig::default())
+    }
+
+    pub fn with_config(seed: u64, config: GameConfig) -> Self {
+        let mut ships = EntityStore::new();
+        let player = ships.spawn(SpaceShip::from_config(&config.ship));
+        Self {
+            ships,
+            player,
+            bullets: EntityStore::new(),
+            asteroids: EntityStore::new(),
+            saucers: EntityStore::new(),
+            score: 0,
+            combo: 1.,
+            lives: 3,
+            wave: 0,
+            bounds: WorldBounds::default(),
+            config,
+            rng: GameRng::seed_from_u64(seed),
+            // The first wave comes in right away
+            wave_timer: f32::INFINITY,
+            respawn_timer: 0.,
+            saucer_timer: 0.,
+            events: EventQueue::new(),
+            broadphase: SpatialHash::default(),
+        }
+    }
+
+    /// A session without a player, shown until a game is started
+    pub fn demo(seed: u64) -> Self {
+        let mut sim = Self::new(seed);
+        sim.ships.clear();
+        sim.lives = 0;
+        sim
+    }
+
+    /// The ship controlled by the player
+    #[cfg(test)]
+    pub fn ship(&self) -> &SpaceShip {
+        self.ships.get(self.player).expect("player ship is in play")
+    }
+
+    /// No asteroid is close enough to the centre to hit a ship entering there
+    fn is_centre_clear(&self) -> bool {
+        let safe_radius = self.config.respawn.safe_radius;
+        self.asteroids.values().all(|asteroid| {
+            let distance = self
+                .bounds
+                .delta(asteroid.obj.position, bevy_math::Vec2::zero())
+                .length();
+            distance - asteroid.bounding_radius() > safe_radius
+        })
+    }
+
+    /// Events raised since the last call
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        self.events.drain()
+    }
+
+    pub fn update(&mut self, input: &UserInput, delta_time: f32) {
+        // Taken out for the duration of the tick so `self` stays borrowable
+        let mut rng = self.rng.clone();
+        let lives_before = self.lives;
+        // Taken off the lives once the hits of the tick are scored
+        let mut ship_lost = false;
+        // One life per tick at most, the ship is gone once hit
+        let vulnerable = move |ship: &SpaceShip, lost: bool| {
+            lives_before > 0 && !lost && !ship.is_invulnerable()
+        };
+
+        // The combo runs out unless hits keep coming
+        let decay = self.config.scoring.combo_decay * delta_time / 1000.;
+        self.combo = (self.combo - decay).max(1.);
+
+        /* Respawn */
+        if self.lives > 0 && !self.ships.contains(self.player) {
+            self.respawn_timer += delta_time;
+            if self.respawn_timer >= self.config.respawn.delay && self.is_centre_clear() {
+                let mut ship = SpaceShip::from_config(&self.config.ship);
+                ship.invulnerable = self.config.respawn.invulnerability;
+                self.player = self.ships.spawn(ship);
+            }
+        }
+
+        if let Some(ship) = self.ships.get_mut(self.player) {
+            // A wrecked ship no longer responds to the controls
+            let alive = self.lives > 0;
+            let config = &self.config.ship;
+
+            /* Keyboard event capture */
+            let mut turn = 0.;
+            if alive && input.keyboard_a {
+                turn -= 1.;
+            }
+            if alive && input.keyboard_d {
+                turn += 1.;
+            }
+            ship.obj.angular_velocity = turn * config.turn_rate;
+            let mut throttle = 0.;
+            if alive && input.keyboard_w {
+                throttle += 1.;
+            }
+            if alive && input.keyboard_s {
+                throttle -= 1.;
+            }
+            ship.obj.acceleration = ship.obj.direction * throttle * config.thrust;
+
+            /* Generate bullets */
+            let player = self.player;
+            let in_flight = self
+                .bullets
+                .values()
+                .filter(|bullet| bullet.owner == Some(Shooter::Ship(player)))
+                .count();
+            let bullet_config = &self.config.bullet;
+            if alive
+                && input.spacebar
+                && ship.last_shot > bullet_config.cooldown
+                && in_flight < bullet_config.max_alive
+            {
+                self.bullets
+                    .spawn(Bullet::fired_by(ship, player, bullet_config));
+                ship.last_shot = 0.;
+                self.events.push(GameEvent::ShotFired {
+                    position: ship.obj.position,
+                    hostile: false,
+                });
+            }
+
+            /* Hyperspace */
+            let hyperspace = &self.config.hyperspace;
+            if alive && input.keyboard_shift && ship.last_jump > hyperspace.cooldown {
+                let from = ship.obj.position;
+                let to = bevy_math::Vec2::new(
+                    rng.gen_range(-self.bounds.half_width, self.bounds.half_width),
+                    rng.gen_range(-self.bounds.half_height, self.bounds.half_height),
+                );
+                ship.obj.teleport(to);
+                ship.obj.velocity = bevy_math::Vec2::zero();
+                ship.last_jump = 0.;
+                let destroyed = rng.gen::<f32>() < hyperspace.self_destruct_chance;
+                ship_lost = destroyed;
+                self.events.push(GameEvent::Hyperspace {
+                    from,
+                    to,
+                    destroyed,
+                });
+            }
+        }
+
+        /* Waves */
+        if self.asteroids.is_empty() {
+            self.wave_timer += delta_time;
+            if self.wave_timer >= self.config.wave.pause {
+                self.wave_timer = 0.;
+                self.wave += 1;
+                self.spawn_wave(&mut rng);
+                self.events.push(GameEvent::WaveStarted { wave: self.wave });
+            }
+        }
+
+        /* Saucers */
+        let config = &self.config.saucer;
+        if self.saucers.is_empty() {
+            self.saucer_timer += delta_time;
+            if self.saucer_timer > config.spawn_interval {
+                self.saucer_timer = 0.;
+                let size = if rng.gen::<f32>() < config.small_chance {
+                    SaucerSize::Small
+                } else {
+                    SaucerSize::Large
+                };
+                let mut saucer = Saucer::new(size);
+                // The left and right edges are the same place, only the course differs
+                saucer.obj.position = bevy_math::Vec2::new(
+                    -self.bounds.half_width,
+                    rng.gen_range(-self.bounds.half_height, self.bounds.half_height),
+                );
+                saucer.course = if rng.gen() { 1. } else { -1. };
+                saucer.distance_left = self.bounds.width();
+                self.saucers.spawn(saucer);
+            }
+        }
+
+        let target = match self.ships.get(self.player) {
+            Some(ship) if self.lives > 0 && !ship_lost => Some(ship.obj.position),
+            _ => None,
+        };
+        for (handle, saucer) in self.saucers.iter_mut() {
+            // Zig-zag across the field
+            if saucer.next_turn <= 0. {
+                saucer.next_turn = config.turn_interval;
+                let vertical = rng.gen_range(-1, 2) as f32;
+                saucer.obj.velocity = bevy_math::Vec2::new(saucer.course, vertical) * config.speed;
+            }
+
+            if saucer.last_shot > config.cooldown {
+                saucer.last_shot = 0.;
+                let angle = match (saucer.size, target) {
+                    // Aims at the ship, getting sharper as the score goes up
+                    (SaucerSize::Small, Some(target)) => {
+                        let delta = self.bounds.delta(saucer.obj.position, target);
+                        let progress =
+                            (self.score as f32 / config.sharpshooter_score as f32).min(1.);
+                        let spread =
+                            config.aim_error + (config.min_aim_error - config.aim_error) * progress;
+                        let mut angle = delta.x().atan2(delta.y()).to_degrees();
+                        if spread > 0. {
+                            angle += rng.gen_range(-spread, spread);
+                        }
+                        angle
+                    }
+                    _ => rng.gen_range(0., 360.),
+                };
+                self.bullets.spawn(Bullet::fired_from(
+                    saucer,
+                    handle,
+                    angle,
+                    config.bullet_speed,
+                    &self.config.bullet,
+                ));
+                self.events.push(GameEvent::ShotFired {
+                    position: saucer.obj.position,
+                    hostile: true,
+                });
+            }
+        }
+
+        /* Despawn objects */
+        // Bullets fizzle out
+        self.bullets.retain(|_, el| !el.is_spent());
+
+        // Bullets interact with asteroids, saucers and the ship
+        self.broadphase.clear();
+        for (handle, asteroid) in self.asteroids.iter() {
+            self.broadphase.insert_wrapped(
+                handle,
+                asteroid.obj.position,
+                asteroid.bounding_radius(),
+                &self.bounds,
+            );
+        }
+        let mut hit_asteroids: Vec<Handle> = vec![];
+        let mut hit_saucers: Vec<Handle> = vec![];
+        let mut candidates: Vec<Handle> = vec![];
+        let mut ship_hit = false;
+        // Base points of every hit, in the order they landed
+        let mut points: Vec<u64> = vec![];
+        let scoring = &self.config.scoring;
+        let ship = match self.ships.get(self.player) {
+            Some(ship) if vulnerable(ship, ship_lost) => Some(ship),
+            _ => None,
+        };
+        let asteroids = &self.asteroids;
+        let saucers = &self.saucers;
+        let broadphase = &self.broadphase;
+        let bounds = &self.bounds;
+        self.bullets.retain(|_, bullet| {
+            let (tip, tail) = bullet.world_trail();
+            broadphase.query_wrapped(
+                (tip + tail) / 2.,
+                (tip - tail).length() / 2.,
+                bounds,
+                &mut candidates,
+            );
+            let hit = candidates.iter().copied().find(|handle| {
+                !hit_asteroids.contains(handle)
+                    && asteroids
+                        .get(*handle)
+                        .unwrap()
+                        .hull()
+                        .is_hit_by(tip, tail, bounds)
+            });
+            if let Some(handle) = hit {
+                // Saucers shooting asteroids score nothing for the player
+                if !bullet.is_hostile() {
+                    points.push(scoring.asteroid(asteroids.get(handle).unwrap().size()));
+                }
+                hit_asteroids.push(handle);
+                return false;
+            }
+
+            if bullet.is_hostile() {
+                match ship {
+                    Some(ship) if ship.hull().is_hit_by(tip, tail, bounds) => {
+                        ship_hit = true;
+                        false
+                    }
+                    _ => true,
+                }
+            } else {
+                let hit = saucers.iter().find(|(handle, saucer)| {
+                    !hit_saucers.contains(handle) && saucer.hull().is_hit_by(tip, tail, bounds)
+                });
+                match hit {
+                    Some((handle, saucer)) => {
+                        points.push(scoring.saucer(saucer.size));
+                        hit_saucers.push(handle);
+                        false
+                    }
+                    None => true,
+                }
+            }
+        });
+        ship_lost |= ship_hit;
+
+        // Saucers crash into the ship and into asteroids
+        for (handle, saucer) in self.saucers.iter() {
+            if hit_saucers.contains(&handle) {
+                continue;
+            }
+            match self.ships.get(self.player) {
+                Some(ship)
+                    if vulnerable(ship, ship_lost)
+                        && saucer.hull().overlaps(&ship.hull(), bounds) =>
+                {
+                    ship_lost = true;
+                    points.push(scoring.saucer(saucer.size));
+                    hit_saucers.push(handle);
+                    continue;
+                }
+                _ => {}
+            }
+            broadphase.query_wrapped(
+                saucer.obj.position,
+                saucer.bounding_radius(),
+                bounds,
+                &mut candidates,
+            );
+            let crashed = candidates.iter().copied().find(|handle| {
+                !hit_asteroids.contains(handle)
+                    && saucer
+                        .hull()
+                        .overlaps(&asteroids.get(*handle).unwrap().hull(), bounds)
+            });
+            if let Some(asteroid) = crashed {
+                hit_asteroids.push(asteroid);
+                hit_saucers.push(handle);
+            }
+        }
+        for handle in hit_saucers {
+            if let Some(saucer) = self.saucers.despawn(handle) {
+                self.events.push(GameEvent::SaucerDestroyed {
+                    size: saucer.size,
+                    position: saucer.obj.position,
+                });
+            }
+        }
+
+        // Split asteroids
+        for handle in hit_asteroids {
+            if let Some(asteroid) = self.asteroids.despawn(handle) {
+                self.events.push(GameEvent::AsteroidDestroyed {
+                    size: asteroid.size(),
+                    position: asteroid.obj.position,
+                });
+                if asteroid.size() != AsteroidSize::Small {
+                    let pieces = rng.gen_range(2, 4);
+                    let radius = asteroid.obj.radius / pieces as f32;
+                    for _ in 0..pieces {
+                        self.spawn_fragment(asteroid.obj.position, radius, &mut rng);
+                    }
+                }
+            }
+        }
+
+        // Clean up asteroids
+        let ship = self.ships.get(self.player);
+        let lost = &mut ship_lost;
+        let bounds = &self.bounds;
+        self.asteroids.retain(|_, el| {
+            // Check overlap with player
+            match ship {
+                Some(ship)
+                    if vulnerable(ship, *lost) && el.hull().overlaps(&ship.hull(), bounds) =>
+                {
+                    *lost = true;
+                    false
+                }
+                _ => true,
+            }
+        });
+
+        // Hits landed before the ship went down still count, extra lives
+        // included
+        for points in points {
+            self.award(points);
+        }
+
+        if ship_lost {
+            self.lives -= 1;
+            if let Some(ship) = self.ships.get(self.player) {
+                self.events.push(GameEvent::ShipDestroyed {
+                    position: ship.obj.position,
+                    lives: self.lives,
+                });
+            }
+        }
+        if ship_lost && self.lives == 0 {
+            // The ship breaks apart into debris
+            if let Some(ship) = self.ships.get_mut(self.player) {
+                ship.obj.scale = bevy_math::vec3(0., 0., 0.);
+                let position = ship.obj.position;
+                for _ in 0..4 {
+                    self.spawn_fragment(position, 0.3, &mut rng);
+                }
+            }
+            self.events.push(GameEvent::GameOver { score: self.score });
+        } else if ship_lost {
+            // Taken off the field until it can safely come back
+            self.ships.despawn(self.player);
+            self.respawn_timer = 0.;
+        }
+
+        /* Position updates */
+        for ship in self.ships.values_mut() {
+            ship.update(delta_time);
+            self.bounds.wrap(&mut ship.obj);
+        }
+        for bullet in self.bullets.values_mut() {
+            bullet.update(delta_time);
+            self.bounds.wrap(&mut bullet.obj);
+        }
+        for asteroid in self.asteroids.values_mut() {
+            asteroid.update(delta_time);
+            self.bounds.wrap(&mut asteroid.obj);
+        }
+        for saucer in self.saucers.values_mut() {
+            saucer.update(delta_time);
+            self.bounds.wrap(&mut saucer.obj);
+        }
+        // Saucers fly off after crossing the field once
+        self.saucers.retain(|_, saucer| !saucer.has_left());
+
+        self.rng = rng;
+    }
+
+    /// Add the points of a hit, feeding the combo and handing out extra lives
+    fn award(&mut self, points: u64) {
+        // Whatever the wreck still hits once the game is over does not count,
+        // the death of the tick itself is applied after its hits
+        if self.lives == 0 {
+            return;
+        }
+        let scoring = &self.config.scoring;
+        let before = self.score;
+        let gained = (points as f32 * self.combo).round() as u64;
+        self.score += gained;
+        if scoring.combo {
+            self.combo = (self.combo + scoring.combo_step).min(scoring.max_combo);
+        }
+        self.events.push(GameEvent::ScoreChanged {
+            score: self.score,
+            gained,
+        });
+        // Thresholds passed by a score, none when extra lives are off
+        let thresholds = |score: u64| score.checked_div(scoring.extra_life_every).unwrap_or(0);
+        let earned = thresholds(self.score) - thresholds(before);
+        let lives = (self.lives as u64 + earned).min(scoring.max_lives as u64) as u8;
+        if lives > self.lives {
+            self.lives = lives;
+            self.events
+                .push(GameEvent::LifeGained { lives: self.lives });
+        }
+    }
+
+    fn spawn_wave<R: Rng>(&mut self, rng: &mut R) {
+        const INIT_RADIUS: f32 = 1.;
+        let speed_factor = self.config.wave.speed_factor(self.wave);
+        for _ in 0..self.config.wave.asteroids(self.wave) {
+            let mut asteroid = Asteroid::new(INIT_RADIUS, rng);
+
+            // Enter along one of the edges, away from the ship in the middle
+            let side = if rng.gen() { 1. } else { -1. };
+            let along = rng.gen_range(-1., 1.);
+            let (x, y) = if rng.gen() {
+                (side, along)
+            } else {
+                (along, side)
+            };
+            asteroid.obj.position =
+                bevy_math::Vec2::new(x * self.bounds.half_width, y * self.bounds.half_height);
+            asteroid.obj.scale = bevy_math::Vec3::new(INIT_RADIUS, INIT_RADIUS, INIT_RADIUS);
+            asteroid.obj.angle = rng.gen_range(0, 360) as f32;
+            asteroid.obj.velocity =
+                heading(asteroid.obj.angle) * rng.gen_range(0.0008, 0.0015) * speed_factor;
+            self.asteroids.spawn(asteroid);
+        }
+    }
+
+    fn spawn_fragment<R: Rng>(&mut self, position: bevy_math::Vec2, radius: f32, rng: &mut R) {
+        let mut asteroid = Asteroid::new(radius, rng);
+        asteroid.obj.position = position;
+        asteroid.obj.angle = rng.gen_range(0, 360) as f32;
+        asteroid.obj.velocity = heading(asteroid.obj.angle) * rng.gen_range(0.001, 0.005);
+        self.asteroids.spawn(asteroid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idle() -> UserInput {
+        UserInput::new()
+    }
+
+    /// A session that never brings in a wave, tests place the asteroids
+    fn calm() -> Simulation {
+        let mut sim = Simulation::new(0);
+        sim.config.wave.pause = f32::INFINITY;
+        sim.wave_timer = 0.;
+        sim
+    }
+
+    #[test]
+    fn next_wave_comes_after_a_pause() {
+        let mut sim = Simulation::new(0);
+        sim.update(&idle(), 0.);
+        assert_eq!(sim.wave, 1);
+        assert_eq!(sim.asteroids.len(), 4);
+        assert_eq!(sim.drain_events(), vec![GameEvent::WaveStarted { wave: 1 }]);
+
+        // Cleared, the next wave waits for the pause to run out
+        sim.asteroids.clear();
+        sim.update(&idle(), 1000.);
+        assert!(sim.asteroids.is_empty());
+        sim.update(&idle(), 1000.);
+        assert_eq!(sim.wave, 2);
+        assert_eq!(sim.asteroids.len(), 6);
+        assert_eq!(sim.drain_events(), vec![GameEvent::WaveStarted { wave: 2 }]);
+    }
+
+    #[test]
+    fn wave_is_not_cleared_while_fragments_remain() {
+        let mut sim = Simulation::new(0);
+        sim.config.wave.first_asteroids = 1;
+        sim.update(&idle(), 0.);
+        let position = sim.asteroids.values().next().unwrap().obj.position;
+        let mut bullet = Bullet::new();
+        bullet.obj.position = position;
+        sim.bullets.spawn(bullet);
+
+        sim.update(&idle(), 0.);
+        assert!(sim.asteroids.len() >= 2);
+        sim.update(&idle(), 5000.);
+        assert_eq!(sim.wave, 1);
+    }
+
+    #[test]
+    fn demo_runs_waves_without_a_ship() {
+        let mut sim = Simulation::demo(0);
+        for _ in 0..100 {
+            sim.update(&idle(), 16.);
+        }
+        assert!(sim.ships.is_empty());
+        assert_eq!(sim.wave, 1);
+        assert!(!sim.asteroids.is_empty());
+    }
+
+    #[test]
+    fn shooting_respects_cooldown() {
+        let mut sim = calm();
+        let mut input = idle();
+        input.spacebar = true;
+
+        // The cooldown has not elapsed yet
+        sim.update(&input, 16.);
+        assert!(sim.bullets.is_empty());
+
+        sim.ships.get_mut(sim.player).unwrap().last_shot = 301.;
+        sim.update(&input, 16.);
+        assert_eq!(sim.bullets.len(), 1);
+        assert_eq!(
+            sim.drain_events(),
+            vec![GameEvent::ShotFired {
+                position: bevy_math::Vec2::zero(),
+                hostile: false,
+            }]
+        );
+        sim.update(&input, 16.);
+        assert_eq!(sim.bullets.len(), 1);
+    }
+
+    #[test]
+    fn bullets_in_flight_are_capped() {
+        let mut sim = calm();
+        sim.config.bullet.cooldown = 0.;
+        sim.config.bullet.max_alive = 3;
+        let mut input = idle();
+        input.spacebar = true;
+        for _ in 0..10 {
+            sim.update(&input, 16.);
+        }
+        assert_eq!(sim.bullets.len(), 3);
+
+        // Once they fizzle out the ship can fire again
+        input.spacebar = false;
+        for _ in 0..60 {
+            sim.update(&input, 16.);
+        }
+        assert!(sim.bullets.is_empty());
+        input.spacebar = true;
+        sim.update(&input, 16.);
+        assert_eq!(sim.bullets.len(), 1);
+    }
+
+    #[test]
+    fn bullets_inherit_ship_velocity_when_configured() {
+        let owner = Simulation::new(0).player;
+        let mut ship = SpaceShip::new();
+        ship.obj.velocity = bevy_math::Vec2::new(0.004, 0.);
+        let mut config = GameConfig::default().bullet;
+
+        config.inherit_velocity = false;
+        let still = Bullet::fired_by(&ship, owner, &config);
+        assert_eq!(still.obj.velocity, bevy_math::Vec2::new(0., config.speed));
+
+        config.inherit_velocity = true;
+        let drifting = Bullet::fired_by(&ship, owner, &config);
+        assert_eq!(
+            drifting.obj.velocity,
+            bevy_math::Vec2::new(0.004, config.speed)
+        );
+    }
+
+    #[test]
+    fn bullets_stop_at_their_range() {
+        let mut sim = calm();
+        let mut bullet = Bullet::new();
+        bullet.obj.velocity = bevy_math::Vec2::new(0.01, 0.);
+        bullet.range = 1.;
+        sim.bullets.spawn(bullet);
+        for _ in 0..6 {
+            sim.update(&idle(), 16.);
+        }
+        assert_eq!(sim.bullets.len(), 1);
+        sim.update(&idle(), 16.);
+        sim.update(&idle(), 16.);
+        assert!(sim.bullets.is_empty());
+    }
+
+    #[test]
+    fn hyperspace_jumps_and_cools_down() {
+        let mut sim = calm();
+        sim.config.hyperspace.self_destruct_chance = 0.;
+        let mut input = idle();
+        input.keyboard_shift = true;
+        sim.ships.get_mut(sim.player).unwrap().last_jump = 2001.;
+        sim.ships.get_mut(sim.player).unwrap().obj.velocity = bevy_math::Vec2::new(0.01, 0.);
+
+        sim.update(&input, 16.);
+        let events = sim.drain_events();
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            GameEvent::Hyperspace { to, destroyed, .. } => {
+                assert!(!destroyed);
+                assert_eq!(sim.ship().obj.position, *to);
+                assert_eq!(sim.ship().obj.previous_position, *to);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(sim.ship().obj.velocity, bevy_math::Vec2::zero());
+        assert_eq!(sim.lives, 3);
+
+        // Held down, but still cooling down
+        sim.update(&input, 16.);
+        assert!(sim.drain_events().is_empty());
+    }
+
+    #[test]
+    fn hyperspace_can_destroy_the_ship() {
+        let mut sim = calm();
+        sim.config.hyperspace.self_destruct_chance = 1.;
+        sim.lives = 1;
+        let mut input = idle();
+        input.keyboard_shift = true;
+        sim.ships.get_mut(sim.player).unwrap().last_jump = 2001.;
+
+        sim.update(&input, 16.);
+
+        assert_eq!(sim.lives, 0);
+        assert_eq!(sim.ship().obj.scale, bevy_math::vec3(0., 0., 0.));
+        let events = sim.drain_events();
+        assert!(matches!(
+            events[..],
+            [
+                GameEvent::Hyperspace {
+                    destroyed: true,
+                    ..
+                },
+                GameEvent::ShipDestroyed { lives: 0, .. },
+                GameEvent::GameOver { score: 0 },
+            ]
+        ));
+    }
+
+    #[test]
+    fn rotation_follows_keyboard() {
+        let mut sim = Simulation::new(0);
+        let mut input = idle();
+        input.keyboard_d = true;
+        let turn = sim.config.ship.turn_rate * 16.;
+        sim.update(&input, 16.);
+        assert!((sim.ship().obj.angle - turn).abs() < 1e-4);
+        input.keyboard_d = false;
+        input.keyboard_a = true;
+        sim.update(&input, 16.);
+        sim.update(&input, 16.);
+        assert!((sim.ship().obj.angle + turn).abs() < 1e-4);
+        input.keyboard_a = false;
+        sim.update(&input, 16.);
+        assert!((sim.ship().obj.angle + turn).abs() < 1e-4);
+    }
+
+    #[test]
+    fn bullet_hit_scores_and_splits_asteroid() {
+        let mut sim = calm();
+        let mut rng = GameRng::seed_from_u64(0);
+        let mut asteroid = Asteroid::new(1., &mut rng);
+        asteroid.obj.position = bevy_math::Vec2::new(3., 3.);
+        sim.asteroids.spawn(asteroid);
+        let mut bullet = Bullet::new();
+        bullet.obj.position = bevy_math::Vec2::new(3., 3.5);
+        sim.bullets.spawn(bullet);
+
+        sim.update(&idle(), 0.);
+
+        let points = sim.config.scoring.large_asteroid;
+        assert_eq!(sim.score, points);
+        assert!(sim.bullets.is_empty());
+        assert!((2..4).contains(&sim.asteroids.len()));
+        assert!(sim.asteroids.values().all(|a| a.obj.radius < 1.));
+        assert_eq!(
+            sim.drain_events(),
+            vec![
+                GameEvent::AsteroidDestroyed {
+                    size: AsteroidSize::Large,
+                    position: bevy_math::Vec2::new(3., 3.),
+                },
+                GameEvent::ScoreChanged {
+                    score: points,
+                    gained: points,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn smaller_asteroids_are_worth_more() {
+        let mut sim = calm();
+        sim.config.scoring.combo = false;
+        let mut rng = GameRng::seed_from_u64(0);
+        for (i, &radius) in [1., 0.5, 0.2].iter().enumerate() {
+            let position = bevy_math::Vec2::new(i as f32 * 2. - 2., 3.);
+            let mut asteroid = Asteroid::new(radius, &mut rng);
+            asteroid.obj.position = position;
+            sim.asteroids.spawn(asteroid);
+            let mut bullet = Bullet::new();
+            bullet.obj.position = position;
+            sim.bullets.spawn(bullet);
+        }
+
+        sim.update(&idle(), 0.);
+
+        let scoring = &sim.config.scoring;
+        assert_eq!(
+            sim.score,
+            scoring.large_asteroid + scoring.medium_asteroid + scoring.small_asteroid
+        );
+    }
+
+    #[test]
+    fn combo_multiplies_points_and_wears_off() {
+        let mut sim = calm();
+        sim.combo = 2.;
+        sim.award(100);
+        assert_eq!(sim.score, 200);
+        assert_eq!(sim.combo, 2.25);
+
+        sim.update(&idle(), 1000.);
+        assert!((sim.combo - 1.25).abs() < 1e-6);
+        sim.update(&idle(), 1000.);
+        assert_eq!(sim.combo, 1.);
+    }
+
+    #[test]
+    fn passing_a_threshold_adds_a_life() {
+        let mut sim = calm();
+        sim.config.scoring.combo = false;
+        sim.score = 9_990;
+        sim.award(20);
+        assert_eq!(sim.lives, 4);
+        assert_eq!(
+            sim.drain_events().last(),
+            Some(&GameEvent::LifeGained { lives: 4 })
+        );
+        sim.award(20);
+        assert_eq!(sim.lives, 4);
+        // Several thresholds at once
+        sim.award(25_000);
+        assert_eq!(sim.lives, 6);
+        // Never past the cap, however many thresholds are passed
+        sim.award(u64::MAX / 2);
+        assert_eq!(sim.lives, sim.config.scoring.max_lives);
+    }
+
+    #[test]
+    fn nothing_scores_after_game_over() {
+        let mut sim = calm();
+        sim.lives = 0;
+        sim.award(20_000);
+        assert_eq!(sim.score, 0);
+        assert_eq!(sim.lives, 0);
+        assert!(sim.drain_events().is_empty());
+    }
+
+    #[test]
+    fn shots_through_outline_gaps_miss() {
+        let mut sim = calm();
+        let mut rng = GameRng::seed_from_u64(0);
+        let mut asteroid = Asteroid::new(1., &mut rng);
+        // Deep notch on the right hand side, well within the bounding circle
+        asteroid.outline = vec![
+            (-1., -1., 0.),
+            (1., -1., 0.),
+            (1., -0.3, 0.),
+            (-0.5, -0.3, 0.),
+            (-0.5, 0.3, 0.),
+            (1., 0.3, 0.),
+            (1., 1., 0.),
+            (-1., 1., 0.),
+        ];
+        asteroid.obj.position = bevy_math::Vec2::new(3., 3.);
+        sim.asteroids.spawn(asteroid);
+        let mut bullet = Bullet::new();
+        // Points along the notch, the trail ends at x = 3.8
+        bullet.obj.angle = 90.;
+        bullet.obj.position = bevy_math::Vec2::new(3.3, 3.);
+        sim.bullets.spawn(bullet);
+
+        sim.update(&idle(), 0.);
+        assert_eq!(sim.score, 0);
+        assert_eq!(sim.bullets.len(), 1);
+
+        // Turned a quarter, the notch faces down and the same shot connects
+        for asteroid in sim.asteroids.values_mut() {
+            asteroid.obj.angle = 90.;
+        }
+        sim.update(&idle(), 0.);
+        assert_eq!(sim.score, sim.config.scoring.large_asteroid);
+    }
+
+    #[test]
+    fn collision_with_ship_costs_a_life() {
+        let mut sim = calm();
+        let mut rng = GameRng::seed_from_u64(0);
+        sim.asteroids.spawn(Asteroid::new(1., &mut rng));
+
+        sim.update(&idle(), 0.);
+
+        assert_eq!(sim.lives, 2);
+        assert!(sim.asteroids.is_empty());
+    }
+
+    #[test]
+    fn lost_ship_comes_back_once_the_centre_is_clear() {
+        let mut sim = calm();
+        let mut rng = GameRng::seed_from_u64(0);
+        sim.asteroids.spawn(Asteroid::new(1., &mut rng));
+        // Touches the ship as well, but only one life is lost per hit
+        let blocker = sim.asteroids.spawn(Asteroid::new(0.1, &mut rng));
+
+        sim.update(&idle(), 0.);
+        assert_eq!(sim.lives, 2);
+        assert!(!sim.ships.contains(sim.player));
+
+        // The delay ran out, but an asteroid sits in the middle
+        sim.update(&idle(), 1000.);
+        assert!(!sim.ships.contains(sim.player));
+
+        sim.asteroids.get_mut(blocker).unwrap().obj.position = bevy_math::Vec2::new(3., 3.);
+        sim.update(&idle(), 16.);
+        assert!(sim.ship().is_invulnerable());
+        assert_eq!(sim.ship().obj.position, bevy_math::Vec2::zero());
+    }
+
+    #[test]
+    fn invulnerable_ship_blinks_and_cannot_be_hit() {
+        let mut sim = calm();
+        sim.ships.get_mut(sim.player).unwrap().invulnerable = 3000.;
+        let mut rng = GameRng::seed_from_u64(0);
+        sim.asteroids.spawn(Asteroid::new(1., &mut rng));
+        let blink_interval = sim.config.respawn.blink_interval;
+
+        let mut shown = vec![];
+        for _ in 0..20 {
+            sim.update(&idle(), 16.);
+            shown.push(sim.ship().is_visible(blink_interval));
+        }
+        assert_eq!(sim.lives, 3);
+        assert!(shown.contains(&true) && shown.contains(&false));
+
+        sim.update(&idle(), 3000.);
+        assert!(sim.ship().is_visible(blink_interval));
+        sim.update(&idle(), 0.);
+        assert_eq!(sim.lives, 2);
+    }
+
+    #[test]
+    fn collides_across_the_world_edge() {
+        let mut sim = calm();
+        let edge = sim.bounds.half_width;
+        sim.ships.get_mut(sim.player).unwrap().obj.position = bevy_math::Vec2::new(edge - 0.1, 0.);
+        let mut rng = GameRng::seed_from_u64(0);
+        let mut asteroid = Asteroid::new(1., &mut rng);
+        asteroid.obj.position = bevy_math::Vec2::new(0.3 - edge, 0.);
+        sim.asteroids.spawn(asteroid);
+
+        sim.update(&idle(), 0.);
+
+        assert_eq!(sim.lives, 2);
+    }
+
+    #[test]
+    fn everything_wraps_and_bullets_fizzle_out() {
+        let mut sim = calm();
+        let mut rng = GameRng::seed_from_u64(0);
+        let mut asteroid = Asteroid::new(0.2, &mut rng);
+        asteroid.obj.position = bevy_math::Vec2::new(0., sim.bounds.half_height - 0.01);
+        asteroid.obj.velocity = bevy_math::Vec2::new(0., 0.001);
+        let handle = sim.asteroids.spawn(asteroid);
+        let mut bullet = Bullet::new();
+        bullet.obj.angle = 90.;
+        bullet.obj.velocity = bevy_math::Vec2::new(0.01, 0.);
+        bullet.obj.position = bevy_math::Vec2::new(0., -2.);
+        bullet.time_to_live = 800.;
+        sim.bullets.spawn(bullet);
+
+        sim.update(&idle(), 16.);
+        let y = sim.asteroids.get(handle).unwrap().obj.position.y();
+        assert!((y + sim.bounds.half_height).abs() < 0.1);
+
+        for _ in 1..50 {
+            sim.update(&idle(), 16.);
+        }
+        // Crossed the whole world, but is still in it
+        let bullet = sim.bullets.values().next().unwrap();
+        assert!(bullet.obj.position.x().abs() <= sim.bounds.half_width);
+        sim.update(&idle(), 16.);
+        sim.update(&idle(), 16.);
+        assert!(sim.bullets.is_empty());
+    }
+
+    #[test]
+    fn losing_last_life_breaks_the_ship_apart() {
+        let mut sim = calm();
+        sim.lives = 1;
+        let mut rng = GameRng::seed_from_u64(0);
+        sim.asteroids.spawn(Asteroid::new(1., &mut rng));
+
+        sim.update(&idle(), 0.);
+
+        assert_eq!(sim.lives, 0);
+        assert_eq!(sim.asteroids.len(), 4);
+        assert_eq!(sim.ship().obj.scale, bevy_math::vec3(0., 0., 0.));
+    }
+
+    #[test]
+    fn hits_of_the_last_tick_still_score() {
+        let mut sim = calm();
+        sim.config.scoring.combo = false;
+        sim.lives = 1;
+        let mut rng = GameRng::seed_from_u64(0);
+        sim.asteroids.spawn(Asteroid::new(1., &mut rng));
+        let position = bevy_math::Vec2::new(-4., 3.);
+        let mut asteroid = Asteroid::new(1., &mut rng);
+        asteroid.obj.position = position;
+        sim.asteroids.spawn(asteroid);
+        let mut bullet = Bullet::new();
+        bullet.obj.position = position;
+        sim.bullets.spawn(bullet);
+
+        sim.update(&idle(), 0.);
+
+        let points = sim.config.scoring.large_asteroid;
+        assert_eq!(sim.lives, 0);
+        assert_eq!(sim.score, points);
+        assert_eq!(
+            sim.drain_events().last(),
+            Some(&GameEvent::GameOver { score: points })
+        );
+    }
+
+    #[test]
+    fn saucers_zig_zag_across_and_leave() {
+        let mut sim = calm();
+        sim.config.saucer.spawn_interval = 0.;
+        sim.config.saucer.cooldown = f32::INFINITY;
+        let speed = sim.config.saucer.speed;
+
+        sim.update(&idle(), 16.);
+        let handle = sim.saucers.handles()[0];
+        let saucer = sim.saucers.get(handle).unwrap();
+        assert!((saucer.obj.position.x().abs() - sim.bounds.half_width).abs() < 0.1);
+
+        let crossing = (sim.bounds.width() / speed / 16.) as usize;
+        for _ in 0..crossing - 2 {
+            sim.update(&idle(), 16.);
+            let velocity = sim.saucers.get(handle).unwrap().obj.velocity;
+            assert!((velocity.x().abs() - speed).abs() < 1e-6);
+            assert!([-speed, 0., speed].contains(&velocity.y()));
+        }
+        for _ in 0..4 {
+            sim.update(&idle(), 16.);
+        }
+        assert!(!sim.saucers.contains(handle));
+    }
+
+    #[test]
+    fn small_saucer_aim_sharpens_with_score() {
+        let mut sim = calm();
+        sim.config.saucer.min_aim_error = 0.;
+        sim.score = sim.config.saucer.sharpshooter_score;
+        let mut saucer = Saucer::new(SaucerSize::Small);
+        saucer.obj.position = bevy_math::Vec2::new(3., 3.);
+        saucer.last_shot = f32::INFINITY;
+        let handle = sim.saucers.spawn(saucer);
+
+        sim.update(&idle(), 0.);
+
+        let bullet = sim.bullets.values().next().unwrap();
+        assert_eq!(bullet.owner, Some(Shooter::Saucer(handle)));
+        let aim = bullet.obj.velocity.normalize();
+        assert!((aim - bevy_math::Vec2::new(-1., -1.).normalize()).length() < 1e-4);
+    }
+
+    #[test]
+    fn saucer_bullets_hit_the_ship_and_asteroids() {
+        let mut sim = calm();
+        let mut saucer = Saucer::new(SaucerSize::Large);
+        saucer.obj.position = bevy_math::Vec2::new(-5., 3.);
+        let owner = sim.saucers.spawn(saucer);
+        let mut rng = GameRng::seed_from_u64(0);
+        let mut asteroid = Asteroid::new(1., &mut rng);
+        asteroid.obj.position = bevy_math::Vec2::new(3., 3.);
+        sim.asteroids.spawn(asteroid);
+        for &position in &[bevy_math::Vec2::zero(), bevy_math::Vec2::new(3., 3.5)] {
+            let mut bullet = Bullet::new();
+            bullet.owner = Some(Shooter::Saucer(owner));
+            bullet.obj.position = position;
+            sim.bullets.spawn(bullet);
+        }
+
+        sim.update(&idle(), 0.);
+
+        assert_eq!(sim.lives, 2);
+        assert_eq!(sim.score, 0);
+        assert!(sim.bullets.is_empty());
+        assert!(sim.asteroids.values().all(|a| a.obj.radius < 1.));
+    }
+
+    #[test]
+    fn shooting_a_saucer_scores_its_value() {
+        let mut sim = calm();
+        let mut saucer = Saucer::new(SaucerSize::Small);
+        saucer.obj.position = bevy_math::Vec2::new(3., 3.);
+        sim.saucers.spawn(saucer);
+        let mut bullet = Bullet::new();
+        bullet.obj.position = bevy_math::Vec2::new(3., 2.9);
+        sim.bullets.spawn(bullet);
+
+        sim.update(&idle(), 0.);
+
+        assert_eq!(sim.score, sim.config.scoring.small_saucer);
+        assert!(sim.saucers.is_empty());
+        assert!(sim.bullets.is_empty());
+    }
+
+    fn play(seed: u64) -> Simulation {
+        let mut sim = Simulation::new(seed);
+        let mut input = idle();
+        for tick in 0..2000 {
+            input.keyboard_w = tick % 300 < 120;
+            input.keyboard_d = tick % 90 < 20;
+            input.spacebar = tick % 40 < 5;
+            sim.update(&input, 16.);
+        }
+        sim
+    }
+
+    fn fingerprint(sim: &Simulation) -> Vec<(f32, f32, f32)> {
+        let mut state = vec![(sim.score as f32, sim.lives as f32, sim.bullets.len() as f32)];
+        for ship in sim.ships.values() {
+            state.push((ship.obj.position.x(), ship.obj.position.y(), ship.obj.angle));
+        }
+        for asteroid in sim.asteroids.values() {
+            state.push((
+                asteroid.obj.position.x(),
+                asteroid.obj.position.y(),
+                asteroid.obj.radius,
+            ));
+            state.extend(asteroid.outline.iter().copied());
+        }
+        state
+    }
+
+    #[test]
+    fn same_seed_replays_identically() {
+        assert_eq!(fingerprint(&play(42)), fingerprint(&play(42)));
+    }
+
+    #[test]
+    fn different_seeds_diverge() {
+        assert_ne!(fingerprint(&play(1)), fingerprint(&play(2)));
+    }
+}
@@ -0,0 +1,118 @@
+use std::convert::TryInto;
+
+use serde::{Deserialize, Serialize};
+
+use crate::programs::asteroid::replay::Recording;
+use crate::programs::asteroid::simulation::{Simulation, RULES_VERSION};
+
+/// Identifies a serialized snapshot
+const MAGIC: &[u8; 4] = b"ASNP";
+/// Version of the byte layout produced by `save`
+pub const FORMAT_VERSION: u8 = 11;
+// magic, format version, rules version
+const HEADER_LEN: usize = 4 + 1 + 2;
+
+/// The whole state of a running session. The recording travels along so a
+/// restored run can still be replayed from its seed.
+///
+/// Generic so saving can borrow the game, serde encodes `&T` exactly like `T`.
+#[derive(Serialize, Deserialize)]
+struct Snapshot<G> {
+    game: G,
+    recording: Vec<u8>,
+}
+
+/// Serialize the session: a small versioned header followed by bincode
+pub fn save(game: &Simulation, recording: &Recording) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(FORMAT_VERSION);
+    bytes.extend_from_slice(&RULES_VERSION.to_le_bytes());
+    // Only `Serialize` impls of plain data are involved, this cannot fail
+    bincode::serialize_into(
+        &mut bytes,
+        &Snapshot {
+            game,
+            recording: recording.encode(),
+        },
+    )
+    .unwrap();
+    bytes
+}
+
+pub fn load(bytes: &[u8]) -> Result<(Simulation, Recording), String> {
+    if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+        return Err(String::from("Data is not an asteroid snapshot"));
+    }
+    let format_version = bytes[4];
+    if format_version != FORMAT_VERSION {
+        return Err(format!(
+            "Unknown snapshot format version {}, expected {}",
+            format_version, FORMAT_VERSION
+        ));
+    }
+    let rules_version = u16::from_le_bytes(bytes[5..7].try_into().unwrap());
+    if rules_version != RULES_VERSION {
+        return Err(format!(
+            "Snapshot was saved with game rules version {}, this build plays version {}",
+            rules_version, RULES_VERSION
+        ));
+    }
+    let snapshot: Snapshot<Simulation> = bincode::deserialize(&bytes[HEADER_LEN..])
+        .map_err(|e| format!("Corrupt snapshot: {}", e))?;
+    Ok((snapshot.game, Recording::decode(&snapshot.recording)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::UserInput;
+
+    fn input_at(tick: u32) -> UserInput {
+        let mut input = UserInput::new();
+        input.keyboard_w = tick % 150 < 60;
+        input.keyboard_d = tick % 50 < 10;
+        input.spacebar = tick % 30 < 4;
+        input
+    }
+
+    #[test]
+    fn restored_session_continues_identically() {
+        let mut game = Simulation::new(11);
+        let mut recording = Recording::new(11);
+        for tick in 0..900 {
+            recording.record(&input_at(tick));
+            game.update(&input_at(tick), 16.);
+        }
+
+        let (mut restored, restored_recording) = load(&save(&game, &recording)).unwrap();
+        assert_eq!(restored_recording, recording);
+
+        for tick in 900..1800 {
+            game.update(&input_at(tick), 16.);
+            restored.update(&input_at(tick), 16.);
+        }
+        assert_eq!(restored.score, game.score);
+        assert_eq!(restored.lives, game.lives);
+        let ships = |sim: &Simulation| {
+            sim.ships
+                .values()
+                .map(|ship| ship.obj.position)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ships(&restored), ships(&game));
+        assert_eq!(restored.bullets.len(), game.bullets.len());
+        assert_eq!(restored.asteroids.handles(), game.asteroids.handles());
+    }
+
+    #[test]
+    fn rejects_foreign_data() {
+        let bytes = save(&Simulation::new(1), &Recording::new(1));
+        assert!(load(&bytes[..HEADER_LEN + 3]).is_err());
+        assert!(load(b"ASNP").is_err());
+
+        let mut bytes = bytes;
+        bytes[4] = FORMAT_VERSION + 1;
+        assert!(matches!(load(&bytes), Err(e) if e.contains("format version")));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::programs::asteroid::events::{EventQueue, GameEvent};
+use crate::programs::asteroid::simulation::Simulation;
+
+/// What the session is up to, as far as the frontend is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    /// Asteroids drift across the screen until a game is started
+    Attract,
+    Playing,
+    Paused,
+    /// The ship was lost and waits for a clear spot to come back
+    Respawning,
+    GameOver,
+}
+
+impl GameState {
+    pub fn name(self) -> &'static str {
+        match self {
+            GameState::Attract => "attract",
+            GameState::Playing => "playing",
+            GameState::Paused => "paused",
+            GameState::Respawning => "respawning",
+            GameState::GameOver => "game_over",
+        }
+    }
+}
+
+/// Moves between the states, either on request of the frontend or by
+/// following the simulation. Every transition is kept as an event.
+pub struct StateMachine {
+    state: GameState,
+    /// Where `resume` goes back to
+    paused_in: GameState,
+    events: EventQueue,
+}
+
+impl StateMachine {
+    pub fn new() -> Self {
+        Self {
+            state: GameState::Attract,
+            paused_in: GameState::Playing,
+            events: EventQueue::new(),
+        }
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    /// Freeze a game in progress, returns whether the state changed
+    pub fn pause(&mut self) -> bool {
+        match self.state {
+            GameState::Playing | GameState::Respawning => {
+                self.paused_in = self.state;
+                self.enter(GameState::Paused);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Continue a paused game, returns whether the state changed
+    pub fn resume(&mut self) -> bool {
+        match self.state {
+            GameState::Paused => {
+                self.enter(self.paused_in);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// A fresh game starts from any state
+    pub fn restart(&mut self) {
+        self.enter(GameState::Playing);
+    }
+
+    /// Catch up with what happened in the simulation during the last tick
+    pub fn follow(&mut self, game: &Simulation) {
+        if let GameState::Playing | GameState::Respawning = self.state {
+            let next = if game.lives == 0 {
+                GameState::GameOver
+            } else if game.ships.contains(game.player) {
+                GameState::Playing
+            } else {
+                GameState::Respawning
+            };
+            self.enter(next);
+        }
+    }
+
+    /// Transitions since the last call
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        self.events.drain()
+    }
+
+    fn enter(&mut self, state: GameState) {
+        if state != self.state {
+            self.events.push(GameEvent::StateChanged {
+                from: self.state,
+                to: state,
+            });
+            self.state = state;
+        }
+    }
+}
+
+impl Default for StateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(machine: &mut StateMachine) -> Vec<(GameState, GameState)> {
+        machine
+            .drain_events()
+            .into_iter()
+            .map(|event| match event {
+                GameEvent::StateChanged { from, to } => (from, to),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pause_only_applies_to_a_game_in_progress() {
+        let mut machine = StateMachine::new();
+        assert!(!machine.pause());
+        machine.restart();
+        assert!(machine.pause());
+        assert!(!machine.pause());
+        assert!(machine.resume());
+        assert!(!machine.resume());
+        assert_eq!(
+            changes(&mut machine),
+            vec![
+                (GameState::Attract, GameState::Playing),
+                (GameState::Playing, GameState::Paused),
+                (GameState::Paused, GameState::Playing),
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_the_ship_through_respawn_and_game_over() {
+        let mut machine = StateMachine::new();
+        let mut game = Simulation::new(0);
+        machine.restart();
+        machine.drain_events();
+
+        game.ships.despawn(game.player);
+        machine.follow(&game);
+        assert_eq!(machine.state(), GameState::Respawning);
+
+        // Pausing while respawning comes back to respawning
+        machine.pause();
+        machine.follow(&game);
+        assert_eq!(machine.state(), GameState::Paused);
+        machine.resume();
+        assert_eq!(machine.state(), GameState::Respawning);
+
+        game.lives = 0;
+        machine.follow(&game);
+        assert_eq!(machine.state(), GameState::GameOver);
+        assert_eq!(
+            changes(&mut machine).last(),
+            Some(&(GameState::Respawning, GameState::GameOver))
+        );
+    }
+}
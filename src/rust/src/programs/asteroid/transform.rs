@@ -1,3 +1,5 @@
+// Mirrors bevy's `Transform`, only a subset of it is used by the renderer
+#![allow(dead_code)]
 use bevy_math::{Mat3, Mat4, Quat, Vec3, Vec4};
 use std::fmt;
 
@@ -0,0 +1,298 @@
+use bevy_math::Mat4;
+use wasm_bindgen::JsCast;
+use web_sys::{AngleInstancedArrays, WebGlRenderingContext as GL, WebGlUniformLocation};
+
+use crate::gl_setup::{GlContext, GlVersion};
+use crate::gpu::{GpuBuffer, GpuProgram};
+use crate::programs::asteroid::backend::{BufferId, BufferUsage, Program, RenderBackend, Uniform};
+use crate::programs::asteroid::batch;
+use crate::programs::asteroid::instancing::INSTANCE_SIZE;
+use crate::programs::asteroid::shaders;
+use crate::programs::asteroid::webgl2::WebGl2Backend;
+use crate::utils::console_log;
+
+/// Backend for `version`, along with the version it ended up drawing with.
+/// `program` must be built from `shaders::sources` for `version`, it draws
+/// `Program::Lines`. A WebGL2 context that fails to set up is drawn through
+/// WebGL1, which it supports all of. Fails if WebGL1 cannot be set up either.
+pub fn create_backend(
+    gl: &GlContext,
+    version: GlVersion,
+    program: GpuProgram,
+) -> Result<(Box<dyn RenderBackend>, GlVersion), String> {
+    let program = match version {
+        GlVersion::WebGl1 => program,
+        GlVersion::WebGl2 => match WebGl2Backend::new(gl, program) {
+            Ok(backend) => return Ok((Box::new(backend), GlVersion::WebGl2)),
+            Err(err) => {
+                console_log(&format!("Falling back to WebGL1: {}", err));
+                let (vertex, fragment) = shaders::sources(GlVersion::WebGl1);
+                GpuProgram::link(gl, vertex, fragment)?
+            }
+        },
+    };
+    let backend = WebGl1Backend::new(gl, program)?;
+    Ok((Box::new(backend), GlVersion::WebGl1))
+}
+
+/// GPU buffer that grows to fit the largest contents written to it
+pub struct GlBuffer {
+    buffer: GpuBuffer,
+    usage: BufferUsage,
+    /// Floats the buffer has room for
+    capacity: usize,
+}
+
+impl GlBuffer {
+    pub fn new(gl: &GlContext, usage: BufferUsage, capacity: usize) -> Self {
+        let mut buffer = Self {
+            buffer: GpuBuffer::new(gl).unwrap(),
+            usage,
+            capacity: 0,
+        };
+        buffer.reserve(gl, capacity);
+        buffer
+    }
+
+    pub fn raw(&self) -> &web_sys::WebGlBuffer {
+        self.buffer.raw()
+    }
+
+    /// Bind the buffer and replace its contents with `data`
+    pub fn write(&mut self, gl: &GlContext, data: &[f32]) {
+        if data.len() > self.capacity {
+            // Double up to avoid growing a little every frame of a busy wave
+            self.reserve(gl, data.len().max(self.capacity * 2));
+        } else {
+            gl.bind_buffer(GL::ARRAY_BUFFER, Some(self.buffer.raw()));
+        }
+        unsafe {
+            let vert_array = js_sys::Float32Array::view(data);
+            gl.buffer_sub_data_with_i32_and_array_buffer_view(GL::ARRAY_BUFFER, 0, &vert_array);
+        }
+    }
+
+    fn reserve(&mut self, gl: &GlContext, capacity: usize) {
+        let usage = match self.usage {
+            BufferUsage::Static => GL::STATIC_DRAW,
+            BufferUsage::Stream => GL::DYNAMIC_DRAW,
+        };
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(self.buffer.raw()));
+        gl.buffer_data_with_i32(
+            GL::ARRAY_BUFFER,
+            (capacity * std::mem::size_of::<f32>()) as i32,
+            usage,
+        );
+        self.capacity = capacity;
+    }
+}
+
+/// Uniform values of the frame, each program catches up before it draws
+#[derive(Default)]
+struct Uniforms {
+    projection: Mat4,
+    view: Mat4,
+    /// Bumped by every upload
+    generation: u32,
+}
+
+/// A WebGL1 program with the locations the backend feeds
+struct Pipeline {
+    program: GpuProgram,
+    attributes: Vec<u32>,
+    projection: WebGlUniformLocation,
+    view: WebGlUniformLocation,
+    /// `Uniforms::generation` last uploaded to the program
+    generation: Option<u32>,
+}
+
+impl Pipeline {
+    fn new(gl: &GlContext, program: GpuProgram, attributes: &[&str]) -> Option<Self> {
+        let attributes = attributes
+            .iter()
+            .map(|name| gl.get_attrib_location(program.raw(), name))
+            .map(|location| {
+                if location < 0 {
+                    None
+                } else {
+                    Some(location as u32)
+                }
+            })
+            .collect::<Option<Vec<u32>>>()?;
+        Some(Self {
+            projection: gl.get_uniform_location(program.raw(), "uPMatrix")?,
+            view: gl.get_uniform_location(program.raw(), "uMVMatrix")?,
+            program,
+            attributes,
+            generation: None,
+        })
+    }
+
+    fn catch_up(&mut self, gl: &GlContext, uniforms: &Uniforms) {
+        if self.generation != Some(uniforms.generation) {
+            let upload = |location, value: &Mat4| {
+                gl.uniform_matrix4fv_with_f32_array(Some(location), false, &value.to_cols_array())
+            };
+            upload(&self.projection, &uniforms.projection);
+            upload(&self.view, &uniforms.view);
+            self.generation = Some(uniforms.generation);
+        }
+    }
+}
+
+/// WebGL1 drawing. Instancing goes through `ANGLE_instanced_arrays` when the
+/// extension is there.
+pub struct WebGl1Backend {
+    gl: GlContext,
+    lines: Pipeline,
+    /// Attributes: vertex position, instance and instance scale
+    instanced: Option<(AngleInstancedArrays, Pipeline)>,
+    current: Option<Program>,
+    uniforms: Uniforms,
+    buffers: Vec<GlBuffer>,
+}
+
+impl WebGl1Backend {
+    pub fn new(gl: &GlContext, program: GpuProgram) -> Result<Self, String> {
+        let lines = Pipeline::new(gl, program, &["aVertexPosition"])
+            .ok_or_else(|| String::from("Line program is missing its inputs"))?;
+        let instanced = Self::instancing(gl);
+        if instanced.is_none() {
+            console_log("ANGLE_instanced_arrays is not available, batching every shape");
+        }
+        Ok(Self {
+            gl: gl.clone(),
+            lines,
+            instanced,
+            current: None,
+            uniforms: Uniforms::default(),
+            buffers: vec![],
+        })
+    }
+
+    fn instancing(gl: &GlContext) -> Option<(AngleInstancedArrays, Pipeline)> {
+        let extension = gl
+            .get_extension("ANGLE_instanced_arrays")
+            .ok()??
+            .dyn_into::<AngleInstancedArrays>()
+            .ok()?;
+        let program = GpuProgram::link(gl, shaders::INSTANCED_V_SHADER, shaders::F_SHADER).ok()?;
+        let pipeline = Pipeline::new(
+            gl,
+            program,
+            &["aVertexPosition", "aInstance", "aInstanceScale"],
+        )?;
+        Some((extension, pipeline))
+    }
+
+    fn pipeline(&mut self) -> &mut Pipeline {
+        match (self.current, &mut self.instanced) {
+            (Some(Program::Instanced), Some((_, pipeline))) => pipeline,
+            _ => &mut self.lines,
+        }
+    }
+}
+
+impl RenderBackend for WebGl1Backend {
+    fn supports_instancing(&self) -> bool {
+        self.instanced.is_some()
+    }
+
+    fn create_buffer(&mut self, usage: BufferUsage, capacity: usize) -> BufferId {
+        self.buffers.push(GlBuffer::new(&self.gl, usage, capacity));
+        BufferId(self.buffers.len() - 1)
+    }
+
+    fn write_buffer(&mut self, buffer: BufferId, data: &[f32]) {
+        self.buffers[buffer.0].write(&self.gl, data);
+    }
+
+    fn use_program(&mut self, program: Program) {
+        self.current = Some(program);
+        let gl = self.gl.clone();
+        gl.use_program(Some(self.pipeline().program.raw()));
+    }
+
+    fn upload_uniform(&mut self, uniform: Uniform, value: &Mat4) {
+        match uniform {
+            Uniform::Projection => self.uniforms.projection = *value,
+            Uniform::View => self.uniforms.view = *value,
+        }
+        self.uniforms.generation = self.uniforms.generation.wrapping_add(1);
+    }
+
+    fn clear(&mut self) {
+        let gl = &self.gl;
+        gl.clear_color(0., 0., 0., 1.);
+        gl.clear_depth(1.);
+        gl.enable(GL::DEPTH_TEST);
+        gl.depth_func(GL::LEQUAL);
+        gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
+    }
+
+    fn draw_lines(&mut self, buffer: BufferId, first: usize, count: usize) {
+        let gl = self.gl.clone();
+        self.lines.catch_up(&gl, &self.uniforms);
+        let vertex_position = self.lines.attributes[0];
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(self.buffers[buffer.0].raw()));
+        gl.enable_vertex_attrib_array(vertex_position);
+        gl.vertex_attrib_pointer_with_i32(
+            vertex_position,
+            batch::VERTEX_SIZE as i32,
+            GL::FLOAT,
+            false,
+            0,
+            0,
+        );
+        gl.draw_arrays(GL::LINES, first as i32, count as i32);
+    }
+
+    fn draw_instanced(
+        &mut self,
+        mesh: BufferId,
+        vertices: usize,
+        instances: BufferId,
+        first: usize,
+        count: usize,
+    ) {
+        let gl = &self.gl;
+        let (extension, pipeline) = match &mut self.instanced {
+            Some(instanced) => instanced,
+            None => return,
+        };
+        pipeline.catch_up(gl, &self.uniforms);
+        let (vertex_position, instance, instance_scale) = (
+            pipeline.attributes[0],
+            pipeline.attributes[1],
+            pipeline.attributes[2],
+        );
+
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(self.buffers[mesh.0].raw()));
+        gl.enable_vertex_attrib_array(vertex_position);
+        gl.vertex_attrib_pointer_with_i32(vertex_position, 3, GL::FLOAT, false, 0, 0);
+
+        // Attribute arrays are global state in WebGL1, only keep them enabled
+        // and stepping per instance for this call
+        let float = std::mem::size_of::<f32>() as i32;
+        let stride = INSTANCE_SIZE as i32 * float;
+        let offset = first as i32 * stride;
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(self.buffers[instances.0].raw()));
+        for &(location, size, start) in [(instance, 4, 0), (instance_scale, 2, 4)].iter() {
+            gl.enable_vertex_attrib_array(location);
+            gl.vertex_attrib_pointer_with_i32(
+                location,
+                size,
+                GL::FLOAT,
+                false,
+                stride,
+                offset + start * float,
+            );
+            extension.vertex_attrib_divisor_angle(location, 1);
+        }
+        extension.draw_arrays_instanced_angle(GL::LINES, 0, vertices as i32, count as i32);
+        for &location in [instance, instance_scale].iter() {
+            extension.vertex_attrib_divisor_angle(location, 0);
+            gl.disable_vertex_attrib_array(location);
+        }
+    }
+}
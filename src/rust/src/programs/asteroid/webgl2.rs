@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use bevy_math::Mat4;
+use web_sys::WebGl2RenderingContext as GL2;
+
+use crate::gl_setup::GlContext;
+use crate::gpu::{GpuBuffer, GpuProgram, GpuVertexArray};
+use crate::programs::asteroid::backend::{BufferId, BufferUsage, Program, RenderBackend, Uniform};
+use crate::programs::asteroid::batch;
+use crate::programs::asteroid::instancing::INSTANCE_SIZE;
+use crate::programs::asteroid::shaders;
+use crate::programs::asteroid::webgl::GlBuffer;
+
+/// Uniform buffer binding point of the `Camera` block
+const CAMERA_BINDING: u32 = 0;
+/// Projection and view matrices
+const CAMERA_SIZE: usize = 32;
+
+// Fixed by `layout(location = ...)` in the shaders
+const VERTEX_POSITION: u32 = 0;
+const INSTANCE: u32 = 1;
+const INSTANCE_SCALE: u32 = 2;
+
+/// WebGL2 drawing: vertex array objects keep the attribute setup, shared
+/// shapes are instanced natively and both programs read the camera from one
+/// uniform buffer
+pub struct WebGl2Backend {
+    gl: GlContext,
+    gl2: GL2,
+    camera: GpuBuffer,
+    line_program: GpuProgram,
+    instanced_program: GpuProgram,
+    buffers: Vec<GlBuffer>,
+    /// Made the first time a buffer is drawn from
+    line_arrays: HashMap<BufferId, GpuVertexArray>,
+    /// Keyed by mesh and instance buffer, the instance pointers move with
+    /// every draw
+    instanced_arrays: HashMap<(BufferId, BufferId), GpuVertexArray>,
+}
+
+impl WebGl2Backend {
+    /// `line_program` is built from `shaders::V_SHADER_300`
+    pub fn new(gl: &GlContext, line_program: GpuProgram) -> Result<Self, String> {
+        let gl2: GL2 = gl
+            .webgl2()
+            .cloned()
+            .ok_or_else(|| String::from("Not a WebGL2 context"))?;
+        let instanced_program =
+            GpuProgram::link(gl, shaders::INSTANCED_V_SHADER_300, shaders::F_SHADER_300)?;
+
+        let camera = GpuBuffer::new(gl)?;
+        gl2.bind_buffer(GL2::UNIFORM_BUFFER, Some(camera.raw()));
+        gl2.buffer_data_with_i32(
+            GL2::UNIFORM_BUFFER,
+            (CAMERA_SIZE * std::mem::size_of::<f32>()) as i32,
+            GL2::DYNAMIC_DRAW,
+        );
+        for program in [&line_program, &instanced_program].iter() {
+            let block = gl2.get_uniform_block_index(program.raw(), "Camera");
+            if block == GL2::INVALID_INDEX {
+                return Err(String::from("Program has no Camera block"));
+            }
+            gl2.uniform_block_binding(program.raw(), block, CAMERA_BINDING);
+        }
+        gl2.bind_buffer_base(GL2::UNIFORM_BUFFER, CAMERA_BINDING, Some(camera.raw()));
+
+        Ok(Self {
+            gl: gl.clone(),
+            gl2,
+            camera,
+            line_program,
+            instanced_program,
+            buffers: vec![],
+            line_arrays: HashMap::new(),
+            instanced_arrays: HashMap::new(),
+        })
+    }
+}
+
+impl RenderBackend for WebGl2Backend {
+    fn supports_instancing(&self) -> bool {
+        true
+    }
+
+    fn create_buffer(&mut self, usage: BufferUsage, capacity: usize) -> BufferId {
+        self.buffers.push(GlBuffer::new(&self.gl, usage, capacity));
+        BufferId(self.buffers.len() - 1)
+    }
+
+    fn write_buffer(&mut self, buffer: BufferId, data: &[f32]) {
+        self.buffers[buffer.0].write(&self.gl, data);
+    }
+
+    fn use_program(&mut self, program: Program) {
+        let program = match program {
+            Program::Lines => &self.line_program,
+            Program::Instanced => &self.instanced_program,
+        };
+        self.gl2.use_program(Some(program.raw()));
+    }
+
+    fn upload_uniform(&mut self, uniform: Uniform, value: &Mat4) {
+        let offset = match uniform {
+            Uniform::Projection => 0,
+            Uniform::View => 16 * std::mem::size_of::<f32>() as i32,
+        };
+        let value = value.to_cols_array();
+        self.gl2
+            .bind_buffer(GL2::UNIFORM_BUFFER, Some(self.camera.raw()));
+        unsafe {
+            let value_array = js_sys::Float32Array::view(&value);
+            self.gl2.buffer_sub_data_with_i32_and_array_buffer_view(
+                GL2::UNIFORM_BUFFER,
+                offset,
+                &value_array,
+            );
+        }
+    }
+
+    fn clear(&mut self) {
+        let gl2 = &self.gl2;
+        gl2.clear_color(0., 0., 0., 1.);
+        gl2.clear_depth(1.);
+        gl2.enable(GL2::DEPTH_TEST);
+        gl2.depth_func(GL2::LEQUAL);
+        gl2.clear(GL2::COLOR_BUFFER_BIT | GL2::DEPTH_BUFFER_BIT);
+    }
+
+    fn draw_lines(&mut self, buffer: BufferId, first: usize, count: usize) {
+        let (gl2, buffers) = (&self.gl2, &self.buffers);
+        let vertex_array = self.line_arrays.entry(buffer).or_insert_with(|| {
+            let vertex_array = GpuVertexArray::new(gl2).unwrap();
+            gl2.bind_vertex_array(Some(vertex_array.raw()));
+            gl2.bind_buffer(GL2::ARRAY_BUFFER, Some(buffers[buffer.0].raw()));
+            gl2.enable_vertex_attrib_array(VERTEX_POSITION);
+            gl2.vertex_attrib_pointer_with_i32(
+                VERTEX_POSITION,
+                batch::VERTEX_SIZE as i32,
+                GL2::FLOAT,
+                false,
+                0,
+                0,
+            );
+            vertex_array
+        });
+        gl2.bind_vertex_array(Some(vertex_array.raw()));
+        gl2.draw_arrays(GL2::LINES, first as i32, count as i32);
+        gl2.bind_vertex_array(None);
+    }
+
+    fn draw_instanced(
+        &mut self,
+        mesh: BufferId,
+        vertices: usize,
+        instances: BufferId,
+        first: usize,
+        count: usize,
+    ) {
+        let (gl2, buffers) = (&self.gl2, &self.buffers);
+        let vertex_array = self
+            .instanced_arrays
+            .entry((mesh, instances))
+            .or_insert_with(|| {
+                let vertex_array = GpuVertexArray::new(gl2).unwrap();
+                gl2.bind_vertex_array(Some(vertex_array.raw()));
+                gl2.bind_buffer(GL2::ARRAY_BUFFER, Some(buffers[mesh.0].raw()));
+                gl2.enable_vertex_attrib_array(VERTEX_POSITION);
+                gl2.vertex_attrib_pointer_with_i32(VERTEX_POSITION, 3, GL2::FLOAT, false, 0, 0);
+                for &location in [INSTANCE, INSTANCE_SCALE].iter() {
+                    gl2.enable_vertex_attrib_array(location);
+                    gl2.vertex_attrib_divisor(location, 1);
+                }
+                vertex_array
+            });
+        gl2.bind_vertex_array(Some(vertex_array.raw()));
+
+        let float = std::mem::size_of::<f32>() as i32;
+        let stride = INSTANCE_SIZE as i32 * float;
+        let offset = first as i32 * stride;
+        gl2.bind_buffer(GL2::ARRAY_BUFFER, Some(buffers[instances.0].raw()));
+        gl2.vertex_attrib_pointer_with_i32(INSTANCE, 4, GL2::FLOAT, false, stride, offset);
+        gl2.vertex_attrib_pointer_with_i32(
+            INSTANCE_SCALE,
+            2,
+            GL2::FLOAT,
+            false,
+            stride,
+            offset + 4 * float,
+        );
+        gl2.draw_arrays_instanced(GL2::LINES, 0, vertices as i32, count as i32);
+        gl2.bind_vertex_array(None);
+    }
+}
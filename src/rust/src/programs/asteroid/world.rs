@@ -0,0 +1,149 @@
+use bevy_math::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::canvas::CanvasData;
+use crate::programs::asteroid::{GameObject, Z_OFFSET};
+
+/// The playing field, centred on the origin. Everything leaving it on one
+/// side comes back in on the opposite one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WorldBounds {
+    pub half_width: f32,
+    pub half_height: f32,
+}
+
+impl WorldBounds {
+    pub fn new(half_width: f32, half_height: f32) -> Self {
+        Self {
+            half_width,
+            half_height,
+        }
+    }
+
+    /// The area visible at `distance` from a camera with a vertical field of
+    /// view of `fov` radians
+    pub fn from_camera(fov: f32, aspect: f32, distance: f32) -> Self {
+        let half_height = (fov / 2.).tan() * distance;
+        Self::new(half_height * aspect, half_height)
+    }
+
+    /// The area the renderer shows on the canvas, where the game plane sits
+    pub fn from_canvas(canvas: &CanvasData) -> Self {
+        Self::from_camera(canvas.get_fov(), canvas.get_aspect(), -Z_OFFSET)
+    }
+
+    pub fn width(&self) -> f32 {
+        self.half_width * 2.
+    }
+
+    pub fn height(&self) -> f32 {
+        self.half_height * 2.
+    }
+
+    pub fn wrap_position(&self, position: Vec2) -> Vec2 {
+        Vec2::new(
+            (position.x() + self.half_width).rem_euclid(self.width()) - self.half_width,
+            (position.y() + self.half_height).rem_euclid(self.height()) - self.half_height,
+        )
+    }
+
+    /// Bring an object that left the field back in from the opposite side
+    pub fn wrap(&self, obj: &mut GameObject) {
+        let wrapped = self.wrap_position(obj.position);
+        if wrapped != obj.position {
+            obj.teleport(wrapped);
+        }
+    }
+
+    /// Shortest displacement leading from `from` to `to`, possibly across an edge
+    pub fn delta(&self, from: Vec2, to: Vec2) -> Vec2 {
+        let shortest = |d: f32, size: f32| d - (d / size).round() * size;
+        let d = to - from;
+        Vec2::new(
+            shortest(d.x(), self.width()),
+            shortest(d.y(), self.height()),
+        )
+    }
+
+    /// Every place a circle shows up at: the circle itself, plus a copy on
+    /// the far side of each edge (and corner) it sticks out of
+    pub fn images(&self, position: Vec2, radius: f32) -> Vec<Vec2> {
+        let shift = |value: f32, half: f32| {
+            if value + radius > half {
+                -2. * half
+            } else if value - radius < -half {
+                2. * half
+            } else {
+                0.
+            }
+        };
+        let dx = shift(position.x(), self.half_width);
+        let dy = shift(position.y(), self.half_height);
+
+        let mut images = vec![position];
+        if dx != 0. {
+            images.push(position + Vec2::new(dx, 0.));
+        }
+        if dy != 0. {
+            images.push(position + Vec2::new(0., dy));
+        }
+        if dx != 0. && dy != 0. {
+            images.push(position + Vec2::new(dx, dy));
+        }
+        images
+    }
+}
+
+impl Default for WorldBounds {
+    /// What the default 1000x600 canvas with a 45° field of view shows
+    fn default() -> Self {
+        Self::from_camera(45f32.to_radians(), 1000. / 600., -Z_OFFSET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_to_the_opposite_edge() {
+        let bounds = WorldBounds::new(5., 3.);
+        let wrapped = bounds.wrap_position(Vec2::new(5.5, -3.25));
+        assert!((wrapped - Vec2::new(-4.5, 2.75)).length() < 1e-5);
+        assert_eq!(bounds.wrap_position(Vec2::new(1., 2.)), Vec2::new(1., 2.));
+
+        let mut obj = GameObject::new();
+        obj.position = Vec2::new(0., 3.5);
+        obj.previous_position = Vec2::new(0., 2.9);
+        bounds.wrap(&mut obj);
+        // No interpolation across the whole screen
+        assert_eq!(obj.previous_position, obj.position);
+        assert!((obj.position.y() + 2.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn delta_takes_the_short_way_round() {
+        let bounds = WorldBounds::new(5., 3.);
+        let delta = bounds.delta(Vec2::new(4.5, 0.), Vec2::new(-4.5, 0.));
+        assert!((delta - Vec2::new(1., 0.)).length() < 1e-5);
+        let delta = bounds.delta(Vec2::new(0., 0.), Vec2::new(1., -1.));
+        assert!((delta - Vec2::new(1., -1.)).length() < 1e-5);
+    }
+
+    #[test]
+    fn images_cover_every_crossed_edge() {
+        let bounds = WorldBounds::new(5., 3.);
+        assert_eq!(bounds.images(Vec2::new(0., 0.), 1.).len(), 1);
+        assert_eq!(bounds.images(Vec2::new(4.5, 0.), 1.).len(), 2);
+        let corner = bounds.images(Vec2::new(-4.5, 2.5), 1.);
+        assert_eq!(corner.len(), 4);
+        assert!(corner.contains(&Vec2::new(5.5, -3.5)));
+    }
+
+    #[test]
+    fn default_matches_the_camera() {
+        let bounds = WorldBounds::default();
+        assert!((bounds.half_height - 4.142).abs() < 1e-3);
+        assert!((bounds.half_width / bounds.half_height - 1000. / 600.).abs() < 1e-5);
+    }
+}
@@ -1,8 +1,7 @@
 use crate::input::UserInput;
-use crate::utils::console_log;
 use nalgebra_glm as glm;
 // use std::time::{SystemTime, UNIX_EPOCH};
-use js_sys::{Date, Function};
+use js_sys::Date;
 
 use super::{
     colors::SingleColor,
@@ -10,9 +9,9 @@ use super::{
 };
 use crate::canvas::CanvasData;
 use crate::transform::Transform;
+use crate::gl_setup::{GlContext, GlVersion};
+use crate::gpu::{GpuBuffer, GpuProgram};
 use crate::RenderObjectTrait;
-use web_sys::WebGlBuffer;
-use web_sys::WebGlProgram;
 use web_sys::WebGlRenderingContext as GL;
 use web_sys::WebGlUniformLocation;
 
@@ -27,42 +26,41 @@ pub struct UniformLocations {
 }
 
 pub struct Box2D {
-    buffer_vertices: WebGlBuffer,
-    buffer_colors: WebGlBuffer,
-    program: WebGlProgram,
+    gl: GlContext,
+    buffer_vertices: GpuBuffer,
+    buffer_colors: GpuBuffer,
+    program: GpuProgram,
     attribute_locations: AttributeLocations,
     uniform_locations: UniformLocations,
     pub transform: Transform,
     pub input: UserInput,
     last_rotation: f64,
-    vertices: Plane2D,
-    colors: [SingleColor; 4],
     square_rotation: f64,
 }
 
 impl Box2D {
     fn init_buffers(
-        gl: &GL,
-        vertices: &Vec<f32>,
+        gl: &GlContext,
+        vertices: &[f32],
         colors: &[SingleColor; 4],
-    ) -> (WebGlBuffer, WebGlBuffer) {
-        let position_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&position_buffer));
+    ) -> (GpuBuffer, GpuBuffer) {
+        let position_buffer = GpuBuffer::new(gl).unwrap();
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(position_buffer.raw()));
 
         unsafe {
-            let vert_array = js_sys::Float32Array::view(&vertices);
+            let vert_array = js_sys::Float32Array::view(vertices);
             gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &vert_array, GL::STATIC_DRAW);
         }
 
-        let color_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&color_buffer));
+        let color_buffer = GpuBuffer::new(gl).unwrap();
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(color_buffer.raw()));
 
         unsafe {
             let mut returnable: Vec<f32> = vec![];
 
             colors.iter().for_each(|p| {
                 p.as_array().iter().for_each(|col| {
-                    returnable.push(col.clone().clone());
+                    returnable.push(**col);
                 });
             });
             let colors_array = js_sys::Float32Array::view(&returnable);
@@ -73,17 +71,17 @@ impl Box2D {
 }
 
 impl RenderObjectTrait for Box2D {
-    fn new(gl: &GL, program: WebGlProgram, transform: Transform) -> Box2D {
+    fn new(gl: &GlContext, _: GlVersion, program: GpuProgram, transform: Transform) -> Result<Box2D, String> {
         let attribute_locations = AttributeLocations {
-            vertex_position: gl.get_attrib_location(&program, "aVertexPosition"),
-            vertex_color: gl.get_attrib_location(&program, "aVertexColor"),
+            vertex_position: gl.get_attrib_location(program.raw(), "aVertexPosition"),
+            vertex_color: gl.get_attrib_location(program.raw(), "aVertexColor"),
         };
         let uniform_locations = UniformLocations {
             projection_matrix: gl
-                .get_uniform_location(&program, "uProjectionMatrix")
+                .get_uniform_location(program.raw(), "uProjectionMatrix")
                 .unwrap(),
             model_view_matrix: gl
-                .get_uniform_location(&program, "uModelViewMatrix")
+                .get_uniform_location(program.raw(), "uModelViewMatrix")
                 .unwrap(),
         };
 
@@ -101,10 +99,10 @@ impl RenderObjectTrait for Box2D {
             SingleColor::new(1., 1., 0.5, 1.),
             SingleColor::new(0., 1., 1., 1.),
         ];
-        let buffer = Box2D::init_buffers(&gl, &vertices.points_as_array(), &colors);
+        let buffer = Box2D::init_buffers(gl, &vertices.points_as_array(), &colors);
 
-        Box2D {
-            vertices,
+        Ok(Box2D {
+            gl: gl.clone(),
             buffer_vertices: buffer.0,
             buffer_colors: buffer.1,
             attribute_locations,
@@ -112,13 +110,13 @@ impl RenderObjectTrait for Box2D {
             program,
             transform,
             input,
-            colors,
             square_rotation: 0.,
-            last_rotation: Date::now() as f64,
-        }
+            last_rotation: Date::now(),
+        })
     }
 
-    fn draw_scene(&mut self, gl: &GL, canvas: &CanvasData) {
+    fn draw_scene(&mut self, canvas: &CanvasData, _: f32) {
+        let gl = &self.gl;
         gl.clear_color(0., 0., 0., 1.);
         gl.clear_depth(1.);
         gl.enable(GL::DEPTH_TEST);
@@ -150,7 +148,7 @@ impl RenderObjectTrait for Box2D {
 
             model_view_matrix = glm::rotate_normalized_axis(
                 &model_view_matrix,
-                self.square_rotation.clone() as f32,
+                self.square_rotation as f32,
                 &rotation_vector,
             );
         }
@@ -163,7 +161,7 @@ impl RenderObjectTrait for Box2D {
             let stride = 0;
             let offset = 0;
 
-            gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.buffer_vertices));
+            gl.bind_buffer(GL::ARRAY_BUFFER, Some(self.buffer_vertices.raw()));
             gl.vertex_attrib_pointer_with_i32(
                 self.attribute_locations.vertex_position as u32,
                 number_components,
@@ -183,7 +181,7 @@ impl RenderObjectTrait for Box2D {
             let stride = 0;
             let offset = 0;
 
-            gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.buffer_colors));
+            gl.bind_buffer(GL::ARRAY_BUFFER, Some(self.buffer_colors.raw()));
             gl.vertex_attrib_pointer_with_i32(
                 self.attribute_locations.vertex_color as u32,
                 number_components,
@@ -195,7 +193,7 @@ impl RenderObjectTrait for Box2D {
             gl.enable_vertex_attrib_array(self.attribute_locations.vertex_color as u32);
         }
 
-        gl.use_program(Some(&self.program));
+        gl.use_program(Some(self.program.raw()));
         let transpose = false;
         gl.uniform_matrix4fv_with_f32_array(
             Some(&self.uniform_locations.projection_matrix),
@@ -225,7 +223,7 @@ impl RenderObjectTrait for Box2D {
     fn set_input(&mut self, input: UserInput) {
         self.input = input;
     }
-    fn update(&mut self, _: f32, _: &GL, _: &CanvasData, _: &Function) {
+    fn update(&mut self, _: f32, _: &CanvasData) {
         // todo!()
     }
 }
@@ -5,49 +5,48 @@ use crate::input::UserInput;
 use crate::programs::box_2d::AttributeLocations;
 use crate::programs::box_2d::UniformLocations;
 use crate::programs::colors::SingleColor;
-use crate::programs::cube::plane::Plane2D;
 use crate::transform::Transform;
-use crate::{utils::console_log, RenderObjectTrait};
-use js_sys::{Date, Function};
+use crate::gl_setup::{GlContext, GlVersion};
+use crate::gpu::{GpuBuffer, GpuProgram};
+use crate::RenderObjectTrait;
+use js_sys::Date;
 use nalgebra_glm as glm;
 use plane::Plane3D;
 use point::Point3D;
-use web_sys::WebGlBuffer;
-use web_sys::WebGlProgram;
 use web_sys::WebGlRenderingContext as GL;
 
 pub struct Cube {
     pub sides: [Plane3D; 6],
 
-    buffer_vertices: WebGlBuffer,
-    buffer_colors: WebGlBuffer,
-    buffer_indices: WebGlBuffer,
-    program: WebGlProgram,
+    gl: GlContext,
+    buffer_vertices: GpuBuffer,
+    buffer_colors: GpuBuffer,
+    buffer_indices: GpuBuffer,
+    program: GpuProgram,
     attribute_locations: AttributeLocations,
     uniform_locations: UniformLocations,
     pub transform: Transform,
     pub input: UserInput,
     last_rotation: f64,
     square_rotation: f64,
-    colors: [SingleColor; 6],
 }
 
 impl Cube {
     fn init_buffers(
-        gl: &GL,
-        vertices: &Vec<f32>,
+        gl: &GlContext,
+        vertices: &[f32],
         colors: &[SingleColor; 6],
-    ) -> (WebGlBuffer, WebGlBuffer, WebGlBuffer) {
-        let position_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&position_buffer));
+    ) -> (GpuBuffer, GpuBuffer, GpuBuffer) {
+        let position_buffer = GpuBuffer::new(gl).unwrap();
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(position_buffer.raw()));
 
         unsafe {
-            let vert_array = js_sys::Float32Array::view(&vertices);
+            let vert_array = js_sys::Float32Array::view(vertices);
             gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &vert_array, GL::STATIC_DRAW);
         }
 
-        let color_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&color_buffer));
+        let color_buffer = GpuBuffer::new(gl).unwrap();
+        gl.bind_buffer(GL::ARRAY_BUFFER, Some(color_buffer.raw()));
 
         unsafe {
             let mut returnable: Vec<f32> = vec![];
@@ -55,10 +54,10 @@ impl Cube {
             colors.iter().for_each(|p| {
                 for _ in 0..4 {
                     let vals = p.as_array();
-                    returnable.push(vals.get(0).unwrap().clone().clone());
-                    returnable.push(vals.get(1).unwrap().clone().clone());
-                    returnable.push(vals.get(2).unwrap().clone().clone());
-                    returnable.push(vals.get(3).unwrap().clone().clone());
+                    returnable.push(*vals[0]);
+                    returnable.push(*vals[1]);
+                    returnable.push(*vals[2]);
+                    returnable.push(*vals[3]);
                 }
             });
 
@@ -66,8 +65,8 @@ impl Cube {
             gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &colors_array, GL::STATIC_DRAW);
         }
 
-        let index_buffer = gl.create_buffer().unwrap();
-        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&index_buffer));
+        let index_buffer = GpuBuffer::new(gl).unwrap();
+        gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(index_buffer.raw()));
         let indices = [
             0, 1, 2, 0, 2, 3, // front
             4, 5, 6, 4, 6, 7, // back
@@ -91,17 +90,17 @@ impl Cube {
 }
 
 impl RenderObjectTrait for Cube {
-    fn new(gl: &GL, program: WebGlProgram, transform: Transform) -> Self {
+    fn new(gl: &GlContext, _: GlVersion, program: GpuProgram, transform: Transform) -> Result<Self, String> {
         let attribute_locations = AttributeLocations {
-            vertex_position: gl.get_attrib_location(&program, "aVertexPosition"),
-            vertex_color: gl.get_attrib_location(&program, "aVertexColor"),
+            vertex_position: gl.get_attrib_location(program.raw(), "aVertexPosition"),
+            vertex_color: gl.get_attrib_location(program.raw(), "aVertexColor"),
         };
         let uniform_locations = UniformLocations {
             projection_matrix: gl
-                .get_uniform_location(&program, "uProjectionMatrix")
+                .get_uniform_location(program.raw(), "uProjectionMatrix")
                 .unwrap(),
             model_view_matrix: gl
-                .get_uniform_location(&program, "uModelViewMatrix")
+                .get_uniform_location(program.raw(), "uModelViewMatrix")
                 .unwrap(),
         };
 
@@ -149,7 +148,7 @@ impl RenderObjectTrait for Cube {
 
         sides.iter().for_each(|el| {
             el.points_as_array().iter().for_each(|p| {
-                vertices.push(p.clone());
+                vertices.push(*p);
             });
         });
 
@@ -163,9 +162,10 @@ impl RenderObjectTrait for Cube {
             SingleColor::new(1., 1., 0., 1.), // top
             SingleColor::new(1., 0., 1., 1.), // bottom
         ];
-        let buffer = Cube::init_buffers(&gl, &vertices, &colors);
+        let buffer = Cube::init_buffers(gl, &vertices, &colors);
 
-        Self {
+        Ok(Self {
+            gl: gl.clone(),
             buffer_vertices: buffer.0,
             buffer_colors: buffer.1,
             buffer_indices: buffer.2,
@@ -174,11 +174,10 @@ impl RenderObjectTrait for Cube {
             program,
             transform,
             input,
-            colors,
             square_rotation: 0.,
-            last_rotation: Date::now() as f64,
+            last_rotation: Date::now(),
             sides,
-        }
+        })
     }
 
     fn input(&mut self) -> &mut UserInput {
@@ -194,7 +193,8 @@ impl RenderObjectTrait for Cube {
         self.input = input;
     }
 
-    fn draw_scene(&mut self, gl: &GL, canvas: &crate::canvas::CanvasData) {
+    fn draw_scene(&mut self, canvas: &crate::canvas::CanvasData, _: f32) {
+        let gl = &self.gl;
         gl.clear_color(0., 0., 0., 1.);
         gl.clear_depth(1.);
         gl.enable(GL::DEPTH_TEST);
@@ -260,7 +260,7 @@ impl RenderObjectTrait for Cube {
             let stride = 0;
             let offset = 0;
 
-            gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.buffer_vertices));
+            gl.bind_buffer(GL::ARRAY_BUFFER, Some(self.buffer_vertices.raw()));
             gl.vertex_attrib_pointer_with_i32(
                 self.attribute_locations.vertex_position as u32,
                 number_components,
@@ -280,7 +280,7 @@ impl RenderObjectTrait for Cube {
             let stride = 0;
             let offset = 0;
 
-            gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.buffer_colors));
+            gl.bind_buffer(GL::ARRAY_BUFFER, Some(self.buffer_colors.raw()));
             gl.vertex_attrib_pointer_with_i32(
                 self.attribute_locations.vertex_color as u32,
                 number_components,
@@ -298,8 +298,8 @@ impl RenderObjectTrait for Cube {
             let buffer_type = GL::UNSIGNED_SHORT;
             let offset = 0;
 
-            gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(&self.buffer_indices));
-            gl.use_program(Some(&self.program));
+            gl.bind_buffer(GL::ELEMENT_ARRAY_BUFFER, Some(self.buffer_indices.raw()));
+            gl.use_program(Some(self.program.raw()));
             let transpose = false;
             gl.uniform_matrix4fv_with_f32_array(
                 Some(&self.uniform_locations.projection_matrix),
@@ -318,7 +318,7 @@ impl RenderObjectTrait for Cube {
             self.last_rotation = now;
         }
     }
-    fn update(&mut self, _: f32, _: &GL, _: &CanvasData, _: &Function) {
+    fn update(&mut self, _: f32, _: &CanvasData) {
         // todo!()
     }
 }
@@ -19,7 +19,7 @@ impl Plane3D {
 
         self.points.iter().for_each(|point| {
             point.as_array().iter().for_each(|coord| {
-                returnable.push(coord.clone());
+                returnable.push(*coord);
             })
         });
         returnable
@@ -41,7 +41,7 @@ impl Plane2D {
 
         self.points.iter().for_each(|point| {
             point.as_array().iter().for_each(|coord| {
-                returnable.push(coord.clone());
+                returnable.push(*coord);
             })
         });
         returnable
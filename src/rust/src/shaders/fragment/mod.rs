@@ -5,3 +5,13 @@ pub const F_SHADER: &str = r#"
       gl_FragColor = vColor;
     }
 "#;
+
+pub const F_SHADER_300: &str = r#"#version 300 es
+    in lowp vec4 vColor;
+
+    out lowp vec4 fragColor;
+
+    void main() {
+      fragColor = vColor;
+    }
+"#;
@@ -13,3 +13,18 @@ pub const V_SHADER: &str = r#"
     }
 "#;
 
+
+pub const V_SHADER_300: &str = r#"#version 300 es
+    in vec4 aVertexPosition;
+    in vec4 aVertexColor;
+
+    uniform mat4 uModelViewMatrix;
+    uniform mat4 uProjectionMatrix;
+
+    out lowp vec4 vColor;
+
+    void main() {
+        gl_Position = uProjectionMatrix * uModelViewMatrix * aVertexPosition;
+        vColor = aVertexColor;
+    }
+"#;
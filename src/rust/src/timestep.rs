@@ -0,0 +1,85 @@
+/// Length of one simulation step in milliseconds. The game was tuned for
+/// 60 updates per second, every per-tick constant assumes this rate.
+pub const SIMULATION_STEP: f32 = 1000. / 60.;
+
+/// Upper bound of steps run for a single frame. Anything beyond that (e.g. a
+/// tab that was in the background) is dropped instead of being caught up.
+pub const MAX_STEPS_PER_FRAME: u32 = 10;
+
+/// Accumulates frame time and hands it out in fixed simulation steps
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: f32,
+    max_steps: u32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32, max_steps: u32) -> Self {
+        Self {
+            step,
+            max_steps,
+            accumulator: 0.,
+        }
+    }
+
+    /// Add the frame time and return how many steps should be simulated
+    pub fn advance(&mut self, delta_time: f32) -> u32 {
+        self.accumulator += delta_time.max(0.);
+        let steps = (self.accumulator / self.step) as u32;
+        if steps > self.max_steps {
+            self.accumulator %= self.step;
+            return self.max_steps;
+        }
+        self.accumulator -= steps as f32 * self.step;
+        steps
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// How far the current frame is between the last two steps, in `0..1`
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.;
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(SIMULATION_STEP, MAX_STEPS_PER_FRAME)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_rate_does_not_change_step_count() {
+        let mut slow = FixedTimestep::new(10., 100);
+        let mut fast = FixedTimestep::new(10., 100);
+        let slow_steps: u32 = (0..50).map(|_| slow.advance(20.)).sum();
+        let fast_steps: u32 = (0..400).map(|_| fast.advance(2.5)).sum();
+        assert_eq!(slow_steps, 100);
+        assert_eq!(fast_steps, 100);
+    }
+
+    #[test]
+    fn leftover_time_becomes_alpha() {
+        let mut timestep = FixedTimestep::new(10., 100);
+        assert_eq!(timestep.advance(25.), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn long_frames_are_clamped() {
+        let mut timestep = FixedTimestep::new(10., 5);
+        assert_eq!(timestep.advance(1003.), 5);
+        assert!(timestep.alpha() < 1.);
+    }
+}
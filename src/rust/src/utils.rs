@@ -1,3 +1,4 @@
+use crate::gl_setup::GlContext;
 use web_sys::WebGlProgram;
 use web_sys::WebGlShader;
 use web_sys::{console, WebGlRenderingContext as GL};
@@ -6,9 +7,7 @@ pub fn console_log(to_log: &str) {
     let array = js_sys::Array::new();
     array.push(&"WASM:".into());
     array.push(&to_log.into());
-    unsafe {
-        console::log(&array);
-    }
+    console::log(&array);
 }
 
 pub fn set_panic_hook() {
@@ -22,17 +21,24 @@ pub fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
-pub fn link_program(gl: &GL, vert_source: &str, frag_source: &str) -> Result<WebGlProgram, String> {
+pub fn link_program(
+    gl: &GlContext,
+    vert_source: &str,
+    frag_source: &str,
+) -> Result<WebGlProgram, String> {
     console_log("Compilign shader");
-    let vertex_shader = compile_shader(gl, GL::VERTEX_SHADER, vert_source).unwrap();
+    let vertex_shader = compile_shader(gl, GL::VERTEX_SHADER, vert_source)?;
     console_log("Compilign shader");
-    let fragment_shader = compile_shader(gl, GL::FRAGMENT_SHADER, frag_source).unwrap();
+    let fragment_shader = compile_shader(gl, GL::FRAGMENT_SHADER, frag_source)?;
     let shader_program = gl
         .create_program()
         .ok_or_else(|| String::from("Error creating program"))?;
     gl.attach_shader(&shader_program, &vertex_shader);
     gl.attach_shader(&shader_program, &fragment_shader);
     gl.link_program(&shader_program);
+    // Flagged for deletion, they go away together with the program
+    gl.delete_shader(Some(&vertex_shader));
+    gl.delete_shader(Some(&fragment_shader));
 
     if !gl
         .get_program_parameter(&shader_program, GL::LINK_STATUS)
@@ -40,16 +46,22 @@ pub fn link_program(gl: &GL, vert_source: &str, frag_source: &str) -> Result<Web
         .unwrap_or(false)
     {
         console_log("Unable to initialize shader program!");
-        Err(gl
+        let log = gl
             .get_program_info_log(&shader_program)
-            .unwrap_or_else(|| String::from("Unknown error occurred when creating program object")))
+            .unwrap_or_else(|| String::from("Unknown error occurred when creating program object"));
+        gl.delete_program(Some(&shader_program));
+        Err(log)
     } else {
         Ok(shader_program)
     }
 }
 
 
-fn compile_shader(context: &GL, shader_type: u32, source: &str) -> Result<WebGlShader, String> {
+fn compile_shader(
+    context: &GlContext,
+    shader_type: u32,
+    source: &str,
+) -> Result<WebGlShader, String> {
     // TODO LEFT OFF  "Initializing the shaders" https://developer.mozilla.org/en-US/docs/Web/API/WebGL_API/Tutorial/Adding_2D_content_to_a_WebGL_context
     // This is the 'loadShader' function
     let shader: WebGlShader = context